@@ -0,0 +1,437 @@
+use anyhow::{anyhow, Result};
+
+use jack_ast::{
+    is_jack_character, BinaryOp, Class, Expression, Identifier, KeywordConstant, Statement,
+    SubroutineCall, SubroutineDec, SubroutineKind, Term, UnaryOp,
+};
+use symbol_table::{Kind, Symbol, SymbolTable};
+use vm_writer::{Command, Segment, VMWriter};
+
+fn segment(kind: Kind) -> Segment {
+    match kind {
+        Kind::Static => Segment::Static,
+        Kind::Field => Segment::This,
+        Kind::Arg => Segment::Argument,
+        Kind::Var => Segment::Local,
+    }
+}
+
+pub struct CodeGenerator {
+    writer: VMWriter,
+    symbol_table: SymbolTable,
+    class_name: String,
+    // if/while文のラベルを関数内で一意にするためのカウンタ
+    label_count: usize,
+}
+
+impl CodeGenerator {
+    // symbol_tableはCompilationEngineが構文解析時に作成したものを受け取る
+    pub fn new(writer: VMWriter, symbol_table: SymbolTable) -> Self {
+        Self {
+            writer,
+            symbol_table,
+            class_name: String::new(),
+            label_count: 0,
+        }
+    }
+
+    pub fn generate(&mut self, class: &Class) -> Result<()> {
+        self.class_name = class.name.name.clone();
+        class
+            .subroutine_decs
+            .iter()
+            .try_for_each(|subroutine_dec| self.generate_subroutine(subroutine_dec))
+    }
+
+    fn generate_subroutine(&mut self, subroutine_dec: &SubroutineDec) -> Result<()> {
+        if !self
+            .symbol_table
+            .enter_subroutine(&subroutine_dec.name.name)
+        {
+            return Err(anyhow!(
+                "{}: subroutine is not found in symbol table: {:?}",
+                subroutine_dec.name.span,
+                subroutine_dec.name.name
+            ));
+        }
+        self.label_count = 0;
+
+        let function_name = format!("{}.{}", self.class_name, subroutine_dec.name.name);
+        let n_vars = self.symbol_table.var_count(Kind::Var);
+        self.writer.write_function(&function_name, n_vars)?;
+        match subroutine_dec.kind {
+            SubroutineKind::Constructor => {
+                let n_fields = self.symbol_table.var_count(Kind::Field);
+                self.writer.write_push(Segment::Constant, n_fields)?;
+                self.writer.write_call("Memory.alloc", 1)?;
+                self.writer.write_pop(Segment::Pointer, 0)?;
+            }
+            SubroutineKind::Method => {
+                self.writer.write_push(Segment::Argument, 0)?;
+                self.writer.write_pop(Segment::Pointer, 0)?;
+            }
+            SubroutineKind::Function => (),
+        }
+        self.generate_statements(&subroutine_dec.body.statements)
+    }
+
+    fn generate_statements(&mut self, statements: &[Statement]) -> Result<()> {
+        statements
+            .iter()
+            .try_for_each(|statement| self.generate_statement(statement))
+    }
+
+    fn generate_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Let(let_statement) => {
+                let variable = self.lookup(&let_statement.name)?;
+                match &let_statement.index {
+                    // arr[i] = expression
+                    Some(index) => {
+                        self.writer
+                            .write_push(segment(variable.kind), variable.index)?;
+                        self.generate_expression(index)?;
+                        self.writer.write_arithmetic(Command::Add)?;
+                        self.generate_expression(&let_statement.value)?;
+                        self.writer.write_pop(Segment::Temp, 0)?;
+                        self.writer.write_pop(Segment::Pointer, 1)?;
+                        self.writer.write_push(Segment::Temp, 0)?;
+                        self.writer.write_pop(Segment::That, 0)?;
+                    }
+                    None => {
+                        self.generate_expression(&let_statement.value)?;
+                        self.writer
+                            .write_pop(segment(variable.kind), variable.index)?;
+                    }
+                }
+            }
+            Statement::If(if_statement) => {
+                let label_id = self.next_label_id();
+                let else_label = format!("IF_ELSE{}", label_id);
+                let end_label = format!("IF_END{}", label_id);
+                self.generate_expression(&if_statement.condition)?;
+                self.writer.write_arithmetic(Command::Not)?;
+                self.writer.write_if(&else_label)?;
+                self.generate_statements(&if_statement.then_statements)?;
+                self.writer.write_goto(&end_label)?;
+                self.writer.write_label(&else_label)?;
+                if let Some(else_statements) = &if_statement.else_statements {
+                    self.generate_statements(else_statements)?;
+                }
+                self.writer.write_label(&end_label)?;
+            }
+            Statement::While(while_statement) => {
+                let label_id = self.next_label_id();
+                let exp_label = format!("WHILE_EXP{}", label_id);
+                let end_label = format!("WHILE_END{}", label_id);
+                self.writer.write_label(&exp_label)?;
+                self.generate_expression(&while_statement.condition)?;
+                self.writer.write_arithmetic(Command::Not)?;
+                self.writer.write_if(&end_label)?;
+                self.generate_statements(&while_statement.statements)?;
+                self.writer.write_goto(&exp_label)?;
+                self.writer.write_label(&end_label)?;
+            }
+            Statement::Do(do_statement) => {
+                self.generate_subroutine_call(&do_statement.call)?;
+                // 戻り値は使わないので捨てる
+                self.writer.write_pop(Segment::Temp, 0)?;
+            }
+            Statement::Return(return_statement) => {
+                match &return_statement.value {
+                    Some(value) => self.generate_expression(value)?,
+                    None => self.writer.write_push(Segment::Constant, 0)?,
+                }
+                self.writer.write_return()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_expression(&mut self, expression: &Expression) -> Result<()> {
+        self.generate_term(&expression.term)?;
+        for (op, term) in &expression.rest {
+            self.generate_term(term)?;
+            match op {
+                BinaryOp::Add => self.writer.write_arithmetic(Command::Add)?,
+                BinaryOp::Sub => self.writer.write_arithmetic(Command::Sub)?,
+                BinaryOp::Mul => self.writer.write_call("Math.multiply", 2)?,
+                BinaryOp::Div => self.writer.write_call("Math.divide", 2)?,
+                BinaryOp::And => self.writer.write_arithmetic(Command::And)?,
+                BinaryOp::Or => self.writer.write_arithmetic(Command::Or)?,
+                BinaryOp::Lt => self.writer.write_arithmetic(Command::Lt)?,
+                BinaryOp::Gt => self.writer.write_arithmetic(Command::Gt)?,
+                BinaryOp::Eq => self.writer.write_arithmetic(Command::Eq)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_term(&mut self, term: &Term) -> Result<()> {
+        match term {
+            Term::IntegerConstant { value, .. } => {
+                self.writer.write_push(Segment::Constant, *value)?;
+            }
+            Term::StringConstant { value, span } => {
+                // 文字セット外の文字は意味解析で報告するが、誤った文字コードは出力しない
+                if let Some(c) = value.chars().find(|c| !is_jack_character(*c)) {
+                    return Err(anyhow!(
+                        "{}: unsupported character in string constant: {:?}",
+                        span,
+                        c
+                    ));
+                }
+                self.writer
+                    .write_push(Segment::Constant, value.len() as u16)?;
+                self.writer.write_call("String.new", 1)?;
+                for c in value.bytes() {
+                    self.writer.write_push(Segment::Constant, c as u16)?;
+                    self.writer.write_call("String.appendChar", 2)?;
+                }
+            }
+            Term::KeywordConstant { keyword, .. } => match keyword {
+                KeywordConstant::True => {
+                    self.writer.write_push(Segment::Constant, 0)?;
+                    self.writer.write_arithmetic(Command::Not)?;
+                }
+                KeywordConstant::False | KeywordConstant::Null => {
+                    self.writer.write_push(Segment::Constant, 0)?;
+                }
+                KeywordConstant::This => {
+                    self.writer.write_push(Segment::Pointer, 0)?;
+                }
+            },
+            Term::VarName(name) => {
+                let variable = self.lookup(name)?;
+                self.writer
+                    .write_push(segment(variable.kind), variable.index)?;
+            }
+            Term::ArrayAccess { name, index, .. } => {
+                let variable = self.lookup(name)?;
+                self.writer
+                    .write_push(segment(variable.kind), variable.index)?;
+                self.generate_expression(index)?;
+                self.writer.write_arithmetic(Command::Add)?;
+                self.writer.write_pop(Segment::Pointer, 1)?;
+                self.writer.write_push(Segment::That, 0)?;
+            }
+            Term::SubroutineCall(call) => self.generate_subroutine_call(call)?,
+            Term::Parenthesized { expression, .. } => self.generate_expression(expression)?,
+            Term::UnaryOp { op, term, .. } => {
+                self.generate_term(term)?;
+                match op {
+                    UnaryOp::Neg => self.writer.write_arithmetic(Command::Neg)?,
+                    UnaryOp::Not => self.writer.write_arithmetic(Command::Not)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_subroutine_call(&mut self, call: &SubroutineCall) -> Result<()> {
+        let n_args = call.arguments.len() as u16;
+        let (function_name, n_args) = match &call.receiver {
+            // subroutineName(...) は自クラスのmethod呼び出し
+            None => {
+                self.writer.write_push(Segment::Pointer, 0)?;
+                (
+                    format!("{}.{}", self.class_name, call.name.name),
+                    n_args + 1,
+                )
+            }
+            Some(receiver) => match self.find(&receiver.name) {
+                // varName.subroutineName(...) はオブジェクトのmethod呼び出し
+                Some(variable) => {
+                    self.writer
+                        .write_push(segment(variable.kind), variable.index)?;
+                    (
+                        format!("{}.{}", variable.var_type, call.name.name),
+                        n_args + 1,
+                    )
+                }
+                // className.subroutineName(...) はfunction,constructor呼び出し
+                None => (format!("{}.{}", receiver.name, call.name.name), n_args),
+            },
+        };
+        call.arguments
+            .iter()
+            .try_for_each(|argument| self.generate_expression(argument))?;
+        self.writer.write_call(&function_name, n_args)
+    }
+
+    fn find(&self, name: &str) -> Option<Symbol> {
+        self.symbol_table.get(name).cloned()
+    }
+
+    fn lookup(&self, identifier: &Identifier) -> Result<Symbol> {
+        self.find(&identifier.name).ok_or_else(|| {
+            anyhow!(
+                "{}: undefined variable: {:?}",
+                identifier.span,
+                identifier.name
+            )
+        })
+    }
+
+    fn next_label_id(&mut self) -> usize {
+        let label_id = self.label_count;
+        self.label_count += 1;
+        label_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compilation_engine::CompilationEngine;
+    use jack_tokenizer::JackTokenizer;
+    use pretty_assertions::assert_eq;
+    use std::{
+        io::Cursor,
+        sync::{Arc, Mutex},
+    };
+
+    fn generate(jack_code: &str) -> Result<String> {
+        let tokenizer = JackTokenizer::new(Cursor::new(jack_code.to_string()))?;
+        let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+        let class = compilation_engine.compile_class()?;
+        let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+        CodeGenerator::new(
+            VMWriter::new(output.clone()),
+            compilation_engine.symbol_table().clone(),
+        )
+        .generate(&class)?;
+        let output = output.lock().unwrap();
+        Ok(String::from_utf8_lossy(output.get_ref()).to_string())
+    }
+
+    #[test]
+    fn test_generate_seven() -> Result<()> {
+        let actual = generate(
+            "class Main {
+    function void main() {
+        do Output.printInt(1 + (2 * 3));
+        return;
+    }
+}",
+        )?;
+        let expect = "function Main.main 0
+push constant 1
+push constant 2
+push constant 3
+call Math.multiply 2
+add
+call Output.printInt 1
+pop temp 0
+push constant 0
+return
+";
+
+        assert_eq!(expect, actual);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_object() -> Result<()> {
+        let actual = generate(
+            r#"class Point {
+    field int x, y;
+    static Array cache;
+    constructor Point new(int ax) {
+        let x = ax;
+        let cache[x] = "a";
+        return this;
+    }
+    method int sum(Point other) {
+        while (~(x > 0)) {
+            let x = x - 1;
+        }
+        if (true) {
+            do draw();
+        } else {
+            return other.sum(this);
+        }
+        return -cache[y];
+    }
+}"#,
+        )?;
+        let expect = "function Point.new 0
+push constant 2
+call Memory.alloc 1
+pop pointer 0
+push argument 0
+pop this 0
+push static 0
+push this 0
+add
+push constant 1
+call String.new 1
+push constant 97
+call String.appendChar 2
+pop temp 0
+pop pointer 1
+push temp 0
+pop that 0
+push pointer 0
+return
+function Point.sum 0
+push argument 0
+pop pointer 0
+label WHILE_EXP0
+push this 0
+push constant 0
+gt
+not
+not
+if-goto WHILE_END0
+push this 0
+push constant 1
+sub
+pop this 0
+goto WHILE_EXP0
+label WHILE_END0
+push constant 0
+not
+not
+if-goto IF_ELSE1
+push pointer 0
+call Point.draw 1
+pop temp 0
+goto IF_END1
+label IF_ELSE1
+push argument 1
+push pointer 0
+call Point.sum 2
+return
+label IF_END1
+push static 0
+push this 1
+add
+pop pointer 1
+push that 0
+neg
+return
+";
+
+        assert_eq!(expect, actual);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_when_undefined_variable() -> Result<()> {
+        let err =
+            generate("class Main { function void main() { let x = 1; return; } }").unwrap_err();
+
+        assert_eq!(err.to_string(), "1:41: undefined variable: \"x\"");
+
+        let err = generate(
+            r#"class Main { function void main() { do Output.printString("é"); return; } }"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1:59: unsupported character in string constant: 'é'"
+        );
+        Ok(())
+    }
+}
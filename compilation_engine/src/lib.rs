@@ -1,492 +1,595 @@
 use anyhow::{anyhow, Ok, Result};
-use jack_tokenizer::{JackTokenizer, KeyWord, TokenType};
-use std::{
-    io::Write,
-    sync::{Arc, Mutex},
+use diagnostics::Diagnostic;
+use jack_ast::{
+    BinaryOp, Class, ClassVarDec, ClassVarKind, DoStatement, Expression, Identifier, IfStatement,
+    KeywordConstant, LetStatement, Parameter, ReturnStatement, ReturnType, Statement,
+    SubroutineBody, SubroutineCall, SubroutineDec, SubroutineKind, Term, Type, UnaryOp, VarDec,
+    WhileStatement,
 };
+use jack_tokenizer::{JackTokenizer, KeyWord, Span, TokenType};
+use symbol_table::{Kind, SymbolTable};
+
+const CLASS_LEVEL_KEYWORDS: [KeyWord; 5] = [
+    KeyWord::Static,
+    KeyWord::Field,
+    KeyWord::Constructor,
+    KeyWord::Function,
+    KeyWord::Method,
+];
+
+const STATEMENT_LEVEL_KEYWORDS: [KeyWord; 11] = [
+    KeyWord::Let,
+    KeyWord::If,
+    KeyWord::While,
+    KeyWord::Do,
+    KeyWord::Return,
+    KeyWord::Var,
+    KeyWord::Static,
+    KeyWord::Field,
+    KeyWord::Constructor,
+    KeyWord::Function,
+    KeyWord::Method,
+];
 
 pub struct CompilationEngine {
     tokenizer: JackTokenizer,
-    writer: Arc<Mutex<dyn Write>>,
+    symbol_table: SymbolTable,
+    class_name: String,
+    diagnostics: Vec<Diagnostic>,
+    // 直前に読み進めたトークンの位置。構文要素のSpanの末尾に使う
+    previous_span: Span,
+    // 直前に読み進めたトークンの直後を指す幅0の位置。columnはトークンの文字数から求める
+    previous_end: Span,
+    // 読み進めたトークン数。エラー回復で読み進められなかった場合の検出に使う
+    consumed: usize,
+    // 最後のトークンを読み進めた後はtokenizerのcurrent_tokenが更新されないので区別する
+    eof: bool,
+    // 解析中の構文要素。構文エラーのメッセージで"after `let` statement"のように使う
+    contexts: Vec<&'static str>,
 }
 
 impl CompilationEngine {
-    pub fn new(tokenizer: JackTokenizer, writer: Arc<Mutex<dyn Write>>) -> Result<Self> {
-        Ok(Self { tokenizer, writer })
+    pub fn new(tokenizer: JackTokenizer) -> Result<Self> {
+        Ok(Self {
+            tokenizer,
+            symbol_table: SymbolTable::new(),
+            class_name: String::new(),
+            diagnostics: Vec::new(),
+            previous_span: Span::default(),
+            previous_end: Span::default(),
+            consumed: 0,
+            eof: false,
+            contexts: Vec::new(),
+        })
     }
 
-    pub fn compile_class(&mut self) -> Result<()> {
-        let tag_name = "class";
-        self.tokenizer.advance()?;
-        self.write_start_xml_tag(tag_name)?;
-        self.process_token("class")?;
-        self.process_identifier()?;
-        self.process_token("{")?;
-        self.compile_class_var_dec()?;
-        self.compile_subroutine()?;
-        self.process_token("}")?;
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+    pub fn symbol_table(&self) -> &SymbolTable {
+        &self.symbol_table
     }
 
-    pub fn compile_class_var_dec(&mut self) -> Result<()> {
-        // "static"|"field"
-        if self.tokenizer.token_type()? == TokenType::KeyWord
-            && matches!(
-                self.tokenizer
-                    .keyword()?
-                    .as_ref()
-                    .to_string()
-                    .to_lowercase()
-                    .as_str(),
-                "static" | "field"
-            )
-        {
-            let tag_name = "classVarDec";
-            self.write_start_xml_tag(tag_name)?;
-
-            self.process_token("static").or_else(|_| {
-                self.process_token("field")?;
-                Ok(())
-            })?;
-            // type -> "int"|"char"|"boolean"|className
-            {
-                self.process_type()?;
+    // 構文エラーが1つでもあればすべての診断をまとめたエラーを返す
+    pub fn compile_class(&mut self) -> Result<Class> {
+        match self.parse_class() {
+            (Some(class), diagnostics) if diagnostics.is_empty() => Ok(class),
+            (_, diagnostics) => Err(anyhow!(diagnostics
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n"))),
+        }
+    }
+
+    // 構文エラーを記録して解析を続け、途中まで構築できた構文木とすべての診断を返す
+    pub fn parse_class(&mut self) -> (Option<Class>, Vec<Diagnostic>) {
+        let class = match self.in_context("class declaration", Self::compile_class_with_recovery) {
+            Result::Ok(class) => Some(class),
+            Err(e) => {
+                self.record(e);
+                None
             }
-            self.process_identifier()?;
-            // 次のトークンを先読みして","であれば複数varNameが存在するので対応する
-            while self.tokenizer.token_type()? == TokenType::Symbol
-                && self.tokenizer.symbol()? == ","
-            {
-                self.process_token(",")?;
-                self.process_identifier()?;
+        };
+        (class, std::mem::take(&mut self.diagnostics))
+    }
+
+    fn compile_class_with_recovery(&mut self) -> Result<Class> {
+        if self.tokenizer.has_more_tokens()? {
+            self.tokenizer.advance()?;
+        } else {
+            self.eof = true;
+        }
+        let start = self.process_token("class")?;
+        let name = self.process_identifier()?;
+        self.class_name = name.name.clone();
+        self.process_token("{")?;
+        let mut class_var_decs = Vec::new();
+        // classVarDecが複数存在する場合
+        while self.is_keyword(&[KeyWord::Static, KeyWord::Field])? {
+            let consumed = self.consumed;
+            match self.in_context("class variable declaration", Self::compile_class_var_dec) {
+                Result::Ok(class_var_dec) => class_var_decs.push(class_var_dec),
+                Err(e) => self.recover(e, consumed, &CLASS_LEVEL_KEYWORDS)?,
             }
-            self.process_token(";")?;
-            self.write_end_xml_tag(tag_name)?;
-            // classVarDecが複数存在する場合
-            if self.tokenizer.token_type()? == TokenType::KeyWord
-                && matches!(
-                    self.tokenizer
-                        .keyword()?
-                        .as_ref()
-                        .to_string()
-                        .to_lowercase()
-                        .as_str(),
-                    "static" | "field"
-                )
-            {
-                self.compile_class_var_dec()?;
+        }
+        let mut subroutine_decs = Vec::new();
+        // subroutineDecが複数存在する場合
+        while self.is_keyword(&[KeyWord::Constructor, KeyWord::Function, KeyWord::Method])? {
+            let consumed = self.consumed;
+            match self.in_context("subroutine declaration", Self::compile_subroutine) {
+                Result::Ok(subroutine_dec) => subroutine_decs.push(subroutine_dec),
+                Err(e) => self.recover(e, consumed, &CLASS_LEVEL_KEYWORDS)?,
             }
         }
+        if let Err(e) = self.process_token("}") {
+            self.record(e);
+        }
 
-        Ok(())
+        Ok(Class {
+            name,
+            class_var_decs,
+            subroutine_decs,
+            span: self.span_from(start),
+        })
+    }
+
+    pub fn compile_class_var_dec(&mut self) -> Result<ClassVarDec> {
+        // "static"|"field"
+        let start = self.current_span();
+        let kind = match self.keyword()? {
+            KeyWord::Static => ClassVarKind::Static,
+            KeyWord::Field => ClassVarKind::Field,
+            _ => return Err(self.syntax_error("`static` or `field`")),
+        };
+        self.advance()?;
+        // type -> "int"|"char"|"boolean"|className
+        let var_type = self.process_type()?;
+        let names = self.process_var_names()?;
+        self.process_token(";")?;
+        let symbol_kind = match kind {
+            ClassVarKind::Static => Kind::Static,
+            ClassVarKind::Field => Kind::Field,
+        };
+        self.define_all(&names, &var_type, symbol_kind);
+
+        Ok(ClassVarDec {
+            kind,
+            var_type,
+            names,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_subroutine(&mut self) -> Result<()> {
+    pub fn compile_subroutine(&mut self) -> Result<SubroutineDec> {
         // "constructor"|"function"|"method"
-        if self.tokenizer.token_type()? == TokenType::KeyWord
-            && matches!(
-                self.tokenizer.keyword()?.as_ref().to_lowercase().as_str(),
-                "constructor" | "function" | "method"
-            )
-        {
-            let tag_name = "subroutineDec";
-            self.write_start_xml_tag(tag_name)?;
-
-            self.process_token("constructor").or_else(|_| {
-                self.process_token("function").or_else(|_| {
-                    self.process_token("method")?;
-                    Ok(())
-                })
-            })?;
-            // "void"|type
-            {
-                self.process_token("void").or_else(|_| {
-                    self.process_type()?;
-                    Ok(())
-                })?;
-            }
-            self.process_identifier()?;
-            self.process_token("(")?;
-            self.compile_parameter_list()?;
-            self.process_token(")")?;
-            self.compile_subroutine_body()?;
-            self.write_end_xml_tag(tag_name)?;
-
-            // subroutineDecが複数存在する場合
-            if self.tokenizer.token_type()? == TokenType::KeyWord
-                && matches!(
-                    self.tokenizer.keyword()?.as_ref().to_lowercase().as_str(),
-                    "constructor" | "function" | "method"
-                )
-            {
-                self.compile_subroutine()?;
-            }
+        let start = self.current_span();
+        let kind = match self.keyword()? {
+            KeyWord::Constructor => SubroutineKind::Constructor,
+            KeyWord::Function => SubroutineKind::Function,
+            KeyWord::Method => SubroutineKind::Method,
+            _ => return Err(self.syntax_error("`constructor`, `function` or `method`")),
+        };
+        self.advance()?;
+        // "void"|type
+        let return_type = if self.is_keyword(&[KeyWord::Void])? {
+            self.advance()?;
+            ReturnType::Void
+        } else {
+            ReturnType::Type(self.process_type()?)
+        };
+        let name = self.process_identifier()?;
+        self.symbol_table.start_subroutine(&name.name);
+        if kind == SubroutineKind::Method {
+            // methodではargument 0にthisが渡される
+            self.symbol_table
+                .define("this", &self.class_name, Kind::Arg);
         }
+        self.process_token("(")?;
+        let parameters = self.in_context("parameter list", Self::compile_parameter_list)?;
+        self.process_token(")")?;
+        let body = self.in_context("subroutine body", Self::compile_subroutine_body)?;
 
-        Ok(())
+        Ok(SubroutineDec {
+            kind,
+            return_type,
+            name,
+            parameters,
+            body,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_parameter_list(&mut self) -> Result<()> {
-        let tag_name = "parameterList";
-        self.write_start_xml_tag(tag_name)?;
+    pub fn compile_parameter_list(&mut self) -> Result<Vec<Parameter>> {
+        let mut parameters = Vec::new();
 
-        if matches!(
-            self.tokenizer.token_type()?,
-            TokenType::KeyWord | TokenType::Identifier
-        ) && matches!(
-            self.tokenizer.keyword()?.as_ref().to_lowercase().as_str(),
-            "int" | "char" | "boolean"
-        ) {
-            self.process_type()?;
-            self.process_identifier()?;
+        // type -> "int"|"char"|"boolean"|className
+        let has_parameter = match self.token_type()? {
+            TokenType::KeyWord => {
+                self.is_keyword(&[KeyWord::Int, KeyWord::Char, KeyWord::Boolean])?
+            }
+            TokenType::Identifier => true,
+            _ => false,
+        };
+        if has_parameter {
+            parameters.push(Parameter {
+                var_type: self.process_type()?,
+                name: self.process_identifier()?,
+            });
             // 次のトークンを先読みして","であれば複数varNameが存在するので対応する
-            while self.tokenizer.token_type()? == TokenType::Symbol
-                && self.tokenizer.symbol()? == ","
-            {
+            while self.is_symbol(&[","])? {
                 self.process_token(",")?;
-                self.process_type()?;
-                self.process_identifier()?;
+                parameters.push(Parameter {
+                    var_type: self.process_type()?,
+                    name: self.process_identifier()?,
+                });
             }
         }
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
-    }
+        parameters.iter().for_each(|parameter| {
+            self.symbol_table
+                .define(&parameter.name.name, parameter.var_type.name(), Kind::Arg);
+        });
 
-    pub fn compile_subroutine_body(&mut self) -> Result<()> {
-        let tag_name = "subroutineBody";
-        self.write_start_xml_tag(tag_name)?;
+        Ok(parameters)
+    }
 
-        self.process_token("{")?;
-        while self.tokenizer.token_type()? == TokenType::KeyWord
-            && self.tokenizer.keyword()?.as_ref().to_lowercase().as_str() == "var"
-        {
-            self.compile_var_dec()?;
+    pub fn compile_subroutine_body(&mut self) -> Result<SubroutineBody> {
+        let start = self.process_token("{")?;
+        let mut var_decs = Vec::new();
+        while self.is_keyword(&[KeyWord::Var])? {
+            let consumed = self.consumed;
+            match self.in_context("`var` declaration", Self::compile_var_dec) {
+                Result::Ok(var_dec) => var_decs.push(var_dec),
+                Err(e) => self.recover(e, consumed, &STATEMENT_LEVEL_KEYWORDS)?,
+            }
         }
-        self.compile_statements()?;
+        let statements = self.compile_statements()?;
         self.process_token("}")?;
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(SubroutineBody {
+            var_decs,
+            statements,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_var_dec(&mut self) -> Result<()> {
-        let tag_name = "varDec";
-        self.write_start_xml_tag(tag_name)?;
-
-        self.process_token("var")?;
-        self.process_type()?;
-        self.process_identifier()?;
-        // 次のトークンを先読みして","であれば複数varNameが存在するので対応する
-        while self.tokenizer.token_type()? == TokenType::Symbol && self.tokenizer.symbol()? == "," {
-            self.process_token(",")?;
-            self.process_identifier()?;
-        }
+    pub fn compile_var_dec(&mut self) -> Result<VarDec> {
+        let start = self.process_token("var")?;
+        let var_type = self.process_type()?;
+        let names = self.process_var_names()?;
         self.process_token(";")?;
+        self.define_all(&names, &var_type, Kind::Var);
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(VarDec {
+            var_type,
+            names,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_statements(&mut self) -> Result<()> {
-        let tag_name = "statements";
-        self.write_start_xml_tag(tag_name)?;
-        if self.tokenizer.token_type()? == TokenType::KeyWord {
-            while self.tokenizer.token_type()? == TokenType::KeyWord
-                && matches!(
-                    self.tokenizer.keyword()?.as_ref().to_lowercase().as_str(),
-                    "let" | "if" | "while" | "do" | "return"
-                )
-            {
-                match self.tokenizer.keyword()? {
-                    jack_tokenizer::KeyWord::Let => self.compile_let()?,
-                    jack_tokenizer::KeyWord::If => self.compile_if()?,
-                    jack_tokenizer::KeyWord::While => self.compile_while()?,
-                    jack_tokenizer::KeyWord::Do => self.compile_do()?,
-                    jack_tokenizer::KeyWord::Return => self.compile_return()?,
-                    _ => (),
-                }
+    pub fn compile_statements(&mut self) -> Result<Vec<Statement>> {
+        let mut statements = Vec::new();
+        while self.is_keyword(&[
+            KeyWord::Let,
+            KeyWord::If,
+            KeyWord::While,
+            KeyWord::Do,
+            KeyWord::Return,
+        ])? {
+            let consumed = self.consumed;
+            match self.compile_statement() {
+                Result::Ok(statement) => statements.push(statement),
+                Err(e) => self.recover(e, consumed, &STATEMENT_LEVEL_KEYWORDS)?,
             }
         }
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(statements)
     }
 
-    pub fn compile_let(&mut self) -> Result<()> {
-        let tag_name = "letStatement";
-        self.write_start_xml_tag(tag_name)?;
+    fn compile_statement(&mut self) -> Result<Statement> {
+        let statement = match self.keyword()? {
+            KeyWord::Let => Statement::Let(self.in_context("`let` statement", Self::compile_let)?),
+            KeyWord::If => Statement::If(self.in_context("`if` statement", Self::compile_if)?),
+            KeyWord::While => {
+                Statement::While(self.in_context("`while` statement", Self::compile_while)?)
+            }
+            KeyWord::Do => Statement::Do(self.in_context("`do` statement", Self::compile_do)?),
+            _ => Statement::Return(self.in_context("`return` statement", Self::compile_return)?),
+        };
+        Ok(statement)
+    }
 
-        self.process_token("let")?;
-        self.process_identifier()?;
-        if self.tokenizer.token_type()? == TokenType::Symbol && self.tokenizer.symbol()? == "[" {
+    pub fn compile_let(&mut self) -> Result<LetStatement> {
+        let start = self.process_token("let")?;
+        // varNameの次のトークンで配列要素への代入かを判定する
+        let has_index = self.peek_is_symbol(0, &["["]);
+        let name = self.process_identifier()?;
+        let index = if has_index {
             self.process_token("[")?;
-            self.compile_expression()?;
+            let index = self.compile_expression()?;
             self.process_token("]")?;
-        }
+            Some(index)
+        } else {
+            None
+        };
         self.process_token("=")?;
-        self.compile_expression()?;
+        let value = self.compile_expression()?;
         self.process_token(";")?;
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(LetStatement {
+            name,
+            index,
+            value,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_if(&mut self) -> Result<()> {
-        let tag_name = "ifStatement";
-        self.write_start_xml_tag(tag_name)?;
-
-        self.process_token("if")?;
+    pub fn compile_if(&mut self) -> Result<IfStatement> {
+        let start = self.process_token("if")?;
         self.process_token("(")?;
-        self.compile_expression()?;
+        let condition = self.compile_expression()?;
         self.process_token(")")?;
         self.process_token("{")?;
-        self.compile_statements()?;
+        let then_statements = self.compile_statements()?;
         self.process_token("}")?;
-        if self.tokenizer.token_type()? == TokenType::KeyWord
-            && self.tokenizer.keyword()? == KeyWord::Else
-        {
+        let else_statements = if self.is_keyword(&[KeyWord::Else])? {
             self.process_token("else")?;
             self.process_token("{")?;
-            self.compile_statements()?;
+            let statements = self.compile_statements()?;
             self.process_token("}")?;
-        }
+            Some(statements)
+        } else {
+            None
+        };
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(IfStatement {
+            condition,
+            then_statements,
+            else_statements,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_while(&mut self) -> Result<()> {
-        let tag_name = "whileStatement";
-        self.write_start_xml_tag(tag_name)?;
-
-        self.process_token("while")?;
+    pub fn compile_while(&mut self) -> Result<WhileStatement> {
+        let start = self.process_token("while")?;
         self.process_token("(")?;
-        self.compile_expression()?;
+        let condition = self.compile_expression()?;
         self.process_token(")")?;
         self.process_token("{")?;
-        self.compile_statements()?;
+        let statements = self.compile_statements()?;
         self.process_token("}")?;
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(WhileStatement {
+            condition,
+            statements,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_do(&mut self) -> Result<()> {
-        let tag_name = "doStatement";
-        self.write_start_xml_tag(tag_name)?;
-
-        self.process_token("do")?;
+    pub fn compile_do(&mut self) -> Result<DoStatement> {
+        let start = self.process_token("do")?;
         // subroutine call
-        {
-            self.process_identifier()?;
-
-            if self.tokenizer.token_type()? == TokenType::Symbol && self.tokenizer.symbol()? == "("
-            {
-                self.process_token("(")?;
-                self.compile_expression_list()?;
-                self.process_token(")")?;
-            } else {
-                self.process_token(".")?;
-                self.process_identifier()?;
-                self.process_token("(")?;
-                self.compile_expression_list()?;
-                self.process_token(")")?;
-            }
-        }
+        let call = self.in_context("subroutine call", Self::compile_subroutine_call)?;
         self.process_token(";")?;
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(DoStatement {
+            call,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_return(&mut self) -> Result<()> {
-        let tag_name = "returnStatement";
-        self.write_start_xml_tag(tag_name)?;
-
-        self.process_token("return")?;
+    pub fn compile_return(&mut self) -> Result<ReturnStatement> {
+        let start = self.process_token("return")?;
         // expression
-        if self.has_expression()? {
-            self.compile_expression()?;
-        }
+        let value = if self.has_expression()? {
+            Some(self.compile_expression()?)
+        } else {
+            None
+        };
         self.process_token(";")?;
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(ReturnStatement {
+            value,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_expression(&mut self) -> Result<()> {
-        let tag_name = "expression";
-        self.write_start_xml_tag(tag_name)?;
-
-        self.compile_term()?;
-        while self.tokenizer.token_type()? == TokenType::Symbol
-            && matches!(
-                self.tokenizer.symbol()?.as_str(),
-                "+" | "-" | "*" | "/" | "&" | "|" | "<" | ">" | "="
-            )
-        {
-            self.process_token(self.tokenizer.symbol()?.as_str())?;
-            self.compile_term()?;
+    pub fn compile_expression(&mut self) -> Result<Expression> {
+        let start = self.current_span();
+        let term = self.compile_term()?;
+        let mut rest = Vec::new();
+        while !self.eof && self.tokenizer.token_type()? == TokenType::Symbol {
+            let Some(op) = BinaryOp::from_symbol(&self.tokenizer.symbol()?) else {
+                break;
+            };
+            self.advance()?;
+            rest.push((op, self.compile_term()?));
         }
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(Expression {
+            term,
+            rest,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn compile_term(&mut self) -> Result<()> {
-        let tag_name = "term";
-        self.write_start_xml_tag(tag_name)?;
-
-        match self.tokenizer.token_type()? {
+    pub fn compile_term(&mut self) -> Result<Term> {
+        let start = self.current_span();
+        let term = match self.token_type()? {
             TokenType::KeyWord => {
-                self.process_token(self.tokenizer.keyword()?.as_ref().to_lowercase().as_str())?;
+                let keyword = match self.keyword()? {
+                    KeyWord::True => KeywordConstant::True,
+                    KeyWord::False => KeywordConstant::False,
+                    KeyWord::Null => KeywordConstant::Null,
+                    KeyWord::This => KeywordConstant::This,
+                    _ => return Err(self.syntax_error("expression")),
+                };
+                self.advance()?;
+                Term::KeywordConstant {
+                    keyword,
+                    span: start,
+                }
             }
             TokenType::Symbol => {
                 let symbol = self.tokenizer.symbol()?;
                 if symbol == "(" {
                     self.process_token("(")?;
-                    self.compile_expression()?;
+                    let expression = self.compile_expression()?;
                     self.process_token(")")?;
+                    Term::Parenthesized {
+                        expression: Box::new(expression),
+                        span: self.span_from(start),
+                    }
+                } else if let Some(op) = UnaryOp::from_symbol(&symbol) {
+                    self.advance()?;
+                    let term = self.compile_term()?;
+                    Term::UnaryOp {
+                        op,
+                        term: Box::new(term),
+                        span: self.span_from(start),
+                    }
                 } else {
-                    self.process_token(&symbol)?;
-                    self.compile_term()?;
+                    return Err(self.syntax_error("expression"));
                 }
             }
+            // varName|varName[expression]|subroutineCallを次のトークンで判定する
             TokenType::Identifier => {
-                self.process_identifier()?;
-                if self.tokenizer.token_type()? == TokenType::Symbol {
-                    match self.tokenizer.symbol()?.as_str() {
-                        "[" => {
-                            self.process_token("[")?;
-                            self.compile_expression()?;
-                            self.process_token("]")?;
-                        }
-                        "(" => {
-                            self.process_token("(")?;
-                            self.compile_expression_list()?;
-                            self.process_token(")")?;
-                        }
-                        "." => {
-                            self.process_token(".")?;
-                            self.process_identifier()?;
-                            self.process_token("(")?;
-                            self.compile_expression_list()?;
-                            self.process_token(")")?;
-                        }
-                        _ => (),
+                if self.peek_is_symbol(0, &["["]) {
+                    let name = self.process_identifier()?;
+                    self.process_token("[")?;
+                    let index = self.compile_expression()?;
+                    self.process_token("]")?;
+                    Term::ArrayAccess {
+                        name,
+                        index: Box::new(index),
+                        span: self.span_from(start),
                     }
+                } else if self.peek_is_symbol(0, &["(", "."]) {
+                    Term::SubroutineCall(
+                        self.in_context("subroutine call", Self::compile_subroutine_call)?,
+                    )
+                } else {
+                    Term::VarName(self.process_identifier()?)
                 }
             }
             TokenType::IntConst => {
-                self.process_token(self.tokenizer.int_val()?.to_string().as_str())?;
+                let value = self.tokenizer.int_val()?;
+                self.advance()?;
+                Term::IntegerConstant { value, span: start }
             }
             TokenType::StringConst => {
-                self.process_token(self.tokenizer.string_val()?.as_str())?;
+                let value = self.tokenizer.string_val()?;
+                self.advance()?;
+                Term::StringConstant { value, span: start }
             }
-        }
+        };
 
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(term)
     }
 
-    pub fn compile_expression_list(&mut self) -> Result<()> {
-        let tag_name = "expressionList";
-        self.write_start_xml_tag(tag_name)?;
+    pub fn compile_expression_list(&mut self) -> Result<Vec<Expression>> {
+        let mut expressions = Vec::new();
         if self.has_expression()? {
-            self.compile_expression()?;
-            while self.tokenizer.token_type()? == TokenType::Symbol
-                && self.tokenizer.symbol()? == ","
-            {
+            expressions.push(self.compile_expression()?);
+            while self.is_symbol(&[","])? {
                 self.process_token(",")?;
-                self.compile_expression()?;
+                expressions.push(self.compile_expression()?);
             }
         }
-        self.write_end_xml_tag(tag_name)?;
-        Ok(())
+        Ok(expressions)
     }
 
-    fn process_token(&mut self, token: &str) -> Result<()> {
-        let current_token = match self.tokenizer.token_type()? {
-            jack_tokenizer::TokenType::KeyWord => self
-                .tokenizer
-                .keyword()?
-                .as_ref()
-                .to_string()
-                .as_str()
-                .to_lowercase(),
-            jack_tokenizer::TokenType::Symbol => self.tokenizer.symbol()?,
-            jack_tokenizer::TokenType::Identifier => self.tokenizer.identifer()?,
-            jack_tokenizer::TokenType::IntConst => self.tokenizer.int_val()?.to_string(),
-            jack_tokenizer::TokenType::StringConst => self.tokenizer.string_val()?,
+    fn compile_subroutine_call(&mut self) -> Result<SubroutineCall> {
+        let start = self.current_span();
+        // (className|varName).subroutineNameの形式かを次のトークンで判定する
+        let (receiver, name) = if self.peek_is_symbol(0, &["."]) {
+            let receiver = self.process_identifier()?;
+            self.process_token(".")?;
+            (Some(receiver), self.process_identifier()?)
+        } else {
+            (None, self.process_identifier()?)
         };
-        match self.tokenizer.token_type()? {
-            TokenType::IntConst | TokenType::StringConst => {
-                self.write_xml(
-                    &self.tokenizer.token_type()?.as_ref().to_string(),
-                    &current_token,
-                )?;
-            }
-            _ => {
-                if current_token == token.to_lowercase() {
-                    self.write_xml(
-                        &self
-                            .tokenizer
-                            .token_type()?
-                            .as_ref()
-                            .to_string()
-                            .to_lowercase(),
-                        self.escape_xml_symbol(&current_token),
-                    )?;
-                } else {
-                    return Err(anyhow!(
-                        "syntax error token: {:?}, current_token: {:?}",
-                        token,
-                        current_token
-                    ));
-                }
-            }
+        self.process_token("(")?;
+        let arguments = self.compile_expression_list()?;
+        self.process_token(")")?;
+
+        Ok(SubroutineCall {
+            receiver,
+            name,
+            arguments,
+            span: self.span_from(start),
+        })
+    }
+
+    fn process_token(&mut self, token: &str) -> Result<Span> {
+        let current_token = self
+            .tokenizer
+            .current_token()
+            .map(|t| t.text.clone())
+            .unwrap_or_default();
+        if self.eof
+            || !matches!(
+                self.tokenizer.token_type()?,
+                TokenType::KeyWord | TokenType::Symbol | TokenType::Identifier
+            )
+            || current_token != token
+        {
+            return Err(self.syntax_error(&format!("`{}`", token)));
         }
 
-        self.tokenizer.advance()?;
-        Ok(())
+        let span = self.current_span();
+        self.advance()?;
+        Ok(span)
     }
 
-    fn process_identifier(&mut self) -> Result<()> {
-        if self.tokenizer.token_type()? == TokenType::Identifier {
-            self.write_xml(
-                &self
-                    .tokenizer
-                    .token_type()?
-                    .as_ref()
-                    .to_string()
-                    .to_lowercase(),
-                &self.tokenizer.identifer()?,
-            )?;
-        } else {
-            return Err(anyhow!(
-                "syntax error current token type is not identifier: {:?}",
-                self.tokenizer.token_type()?
-            ));
+    fn process_identifier(&mut self) -> Result<Identifier> {
+        if self.eof {
+            return Err(self.syntax_error("identifier"));
+        }
+        if self.token_type()? != TokenType::Identifier {
+            return Err(self.syntax_error("identifier"));
         }
-        self.tokenizer.advance()?;
+        let identifier = Identifier {
+            name: self.tokenizer.identifer()?,
+            span: self.current_span(),
+        };
+        self.advance()?;
 
-        Ok(())
+        Ok(identifier)
     }
 
-    fn process_type(&mut self) -> Result<()> {
-        self.process_token("int").or_else(|_| {
-            self.process_token("char").or_else(|_| {
-                self.process_token("boolean").or_else(|_| {
-                    self.process_identifier()?;
-                    Ok(())
-                })
-            })
-        })?;
-        Ok(())
+    fn process_type(&mut self) -> Result<Type> {
+        let var_type = match self.token_type()? {
+            TokenType::KeyWord => match self.keyword()? {
+                KeyWord::Int => Type::Int,
+                KeyWord::Char => Type::Char,
+                KeyWord::Boolean => Type::Boolean,
+                _ => return Err(self.syntax_error("type")),
+            },
+            _ => return Ok(Type::ClassName(self.process_identifier()?)),
+        };
+        self.advance()?;
+        Ok(var_type)
+    }
+
+    // varName (',' varName)*
+    fn process_var_names(&mut self) -> Result<Vec<Identifier>> {
+        let mut names = vec![self.process_identifier()?];
+        // 次のトークンを先読みして","であれば複数varNameが存在するので対応する
+        while self.is_symbol(&[","])? {
+            self.process_token(",")?;
+            names.push(self.process_identifier()?);
+        }
+        Ok(names)
+    }
+
+    fn define_all(&mut self, names: &[Identifier], var_type: &Type, kind: Kind) {
+        names.iter().for_each(|name| {
+            self.symbol_table.define(&name.name, var_type.name(), kind);
+        });
     }
 
     fn has_expression(&self) -> Result<bool> {
+        if self.eof {
+            return Ok(false);
+        }
         match self.tokenizer.token_type()? {
             TokenType::KeyWord => Ok(matches!(
                 self.tokenizer.keyword()?,
@@ -499,66 +602,427 @@ impl CompilationEngine {
         }
     }
 
-    fn escape_xml_symbol<'a>(&self, v: &'a str) -> &'a str {
-        match v {
-            "<" => "&lt;",
-            ">" => "&gt;",
-            "\"" => "&quot;",
-            "&" => "&amp;",
-            _ => v,
+    fn is_keyword(&self, keywords: &[KeyWord]) -> Result<bool> {
+        Ok(!self.eof
+            && self.tokenizer.token_type()? == TokenType::KeyWord
+            && keywords.contains(&self.tokenizer.keyword()?))
+    }
+
+    fn is_symbol(&self, symbols: &[&str]) -> Result<bool> {
+        Ok(!self.eof
+            && self.tokenizer.token_type()? == TokenType::Symbol
+            && symbols.contains(&self.tokenizer.symbol()?.as_str()))
+    }
+
+    // 現在のトークンを読み進めずにn個先のトークンが指定の記号か判定する
+    fn peek_is_symbol(&self, n: usize, symbols: &[&str]) -> bool {
+        !self.eof
+            && self.tokenizer.peek(n).is_some_and(|token| {
+                token.kind() == TokenType::Symbol && symbols.contains(&token.text.as_str())
+            })
+    }
+
+    fn token_type(&self) -> Result<TokenType> {
+        if self.eof {
+            return Err(self.syntax_error("a token"));
         }
+        self.tokenizer.token_type()
     }
 
-    fn write_start_xml_tag(&mut self, tag_name: &str) -> Result<()> {
-        self.write(&format!("<{tag_name}>\n"))?;
-        Ok(())
+    fn keyword(&self) -> Result<KeyWord> {
+        if self.token_type()? != TokenType::KeyWord {
+            return Err(self.syntax_error("a keyword"));
+        }
+        self.tokenizer.keyword()
     }
 
-    fn write_end_xml_tag(&mut self, tag_name: &str) -> Result<()> {
-        self.write(&format!("</{tag_name}>\n"))?;
+    fn advance(&mut self) -> Result<()> {
+        self.previous_end = match self.tokenizer.current_token() {
+            // Spanのstart,endはバイトオフセットなので、非ASCIIの文字列定数でも列がずれないように数える
+            Some(token) if !self.eof => Span {
+                start: token.span.end,
+                end: token.span.end,
+                column: token.span.column + token.text.chars().count(),
+                ..token.span
+            },
+            _ => self.current_span(),
+        };
+        self.previous_span = self.current_span();
+        self.consumed += 1;
+        if self.tokenizer.has_more_tokens()? {
+            self.tokenizer.advance()?;
+        } else {
+            self.eof = true;
+        }
         Ok(())
     }
 
-    fn write_xml(&mut self, tag_name: &str, content: &str) -> Result<()> {
-        self.write(&format!("<{tag_name}>"))?;
-        self.write(&format!(" {content} "))?;
-        self.write(&format!("</{tag_name}>\n"))?;
+    // expectedは期待した構文要素の説明。"expected `;` after `let` statement, found `}`"のように報告する
+    fn syntax_error(&self, expected: &str) -> anyhow::Error {
+        let found = match self.tokenizer.current_token() {
+            Some(token) if !self.eof => format!("`{}`", token.text),
+            _ => "end of input".to_string(),
+        };
+        // ";"は文や宣言の終端なので"after"、それ以外は"in"で構文要素を示す
+        let message = match self.contexts.last() {
+            Some(context) if expected == "`;`" => {
+                format!("expected {} after {}, found {}", expected, context, found)
+            }
+            Some(context) => format!("expected {} in {}, found {}", expected, context, found),
+            None => format!("expected {}, found {}", expected, found),
+        };
+        let diagnostic = Diagnostic::error(message, self.current_span()).with_code("syntax-error");
+        if expected == "`;`" && self.consumed > 0 {
+            // 直前のトークンの直後に挿入する
+            return diagnostic
+                .with_suggestion("add `;` here", ";", self.previous_end)
+                .into();
+        }
+        diagnostic.into()
+    }
+
+    // 構文要素を解析する間だけ、構文エラーのメッセージに使う文脈を積む
+    fn in_context<T>(
+        &mut self,
+        context: &'static str,
+        compile: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.contexts.push(context);
+        let result = compile(self);
+        self.contexts.pop();
+        result
+    }
+
+    fn record(&mut self, e: anyhow::Error) {
+        let diagnostic = match e.downcast::<Diagnostic>() {
+            Result::Ok(diagnostic) => diagnostic,
+            Err(e) => {
+                Diagnostic::error(e.to_string(), self.current_span()).with_code("syntax-error")
+            }
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    // 診断を記録し、次の構文要素の先頭までトークンを読み飛ばす
+    fn recover(&mut self, e: anyhow::Error, consumed: usize, keywords: &[KeyWord]) -> Result<()> {
+        self.record(e);
+        self.synchronize(keywords)?;
+        if self.consumed == consumed && !self.eof {
+            self.advance()?;
+        }
         Ok(())
     }
 
-    fn write(&mut self, content: &str) -> Result<()> {
-        self.writer.lock().unwrap().write(content.as_bytes())?;
-        self.writer.lock().unwrap().flush()?;
+    // 同じ階層の";"の直後、"}"、またはkeywordsのいずれかの位置まで読み飛ばす
+    fn synchronize(&mut self, keywords: &[KeyWord]) -> Result<()> {
+        let mut depth = 0;
+        while !self.eof {
+            if depth == 0 {
+                if self.is_symbol(&[";"])? {
+                    return self.advance();
+                }
+                if self.is_symbol(&["}"])? || self.is_keyword(keywords)? {
+                    return Ok(());
+                }
+            }
+            if self.is_symbol(&["{"])? {
+                depth += 1;
+            } else if self.is_symbol(&["}"])? {
+                depth -= 1;
+            }
+            self.advance()?;
+        }
         Ok(())
     }
+
+    fn span_from(&self, start: Span) -> Span {
+        start.join(self.previous_span)
+    }
+
+    fn current_span(&self) -> Span {
+        if self.eof {
+            // 入力の末尾を指す
+            return self.previous_end;
+        }
+        self.tokenizer.current_span().unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
-    use std::{
-        io::Cursor,
-        sync::{Arc, Mutex},
-    };
+    use std::io::Cursor;
 
-    use jack_tokenizer::JackTokenizer;
+    use jack_ast::{Identifier, Parameter, Statement, Term, Type};
+    use jack_tokenizer::{JackTokenizer, Span};
+    use symbol_table::Kind;
 
     use crate::CompilationEngine;
     use anyhow::Result;
 
+    fn compilation_engine(jack_code: &str) -> Result<CompilationEngine> {
+        let mut tokenizer = JackTokenizer::new(Cursor::new(jack_code.to_string()))?;
+        tokenizer.advance()?;
+        CompilationEngine::new(tokenizer)
+    }
+
     #[test]
     fn test_compilation_engine() -> Result<()> {
-        let jack_code = Cursor::new("method");
-        let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
-        let mut tokenizer = JackTokenizer::new(jack_code)?;
-        tokenizer.advance()?;
-        let mut compilation_engine = CompilationEngine::new(tokenizer, output.clone())?;
-        compilation_engine.process_token("method")?;
-        let expect = "<keyword> method </keyword>\n";
-        let output = output.lock().unwrap();
-        let actual = String::from_utf8_lossy(output.get_ref());
+        let mut compilation_engine = compilation_engine("method")?;
+        let span = compilation_engine.process_token("method")?;
+
+        assert_eq!(
+            span,
+            Span {
+                start: 0,
+                end: 6,
+                line: 1,
+                column: 1
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compile_parameter_list_when_class_type() -> Result<()> {
+        let mut compilation_engine = compilation_engine("Point p, int x)")?;
+        let parameters = compilation_engine.compile_parameter_list()?;
+        let ident = |name: &str, start: usize| Identifier {
+            name: name.to_string(),
+            span: Span {
+                start,
+                end: start + name.len(),
+                line: 1,
+                column: start + 1,
+            },
+        };
+
+        assert_eq!(
+            parameters,
+            vec![
+                Parameter {
+                    var_type: Type::ClassName(ident("Point", 0)),
+                    name: ident("p", 6),
+                },
+                Parameter {
+                    var_type: Type::Int,
+                    name: ident("x", 13),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compile_term_when_subroutine_call() -> Result<()> {
+        let mut compilation_engine = compilation_engine("game.run(1, a[2]);")?;
+        let Term::SubroutineCall(call) = compilation_engine.compile_term()? else {
+            panic!("term is not subroutine call");
+        };
+
+        assert_eq!(call.receiver.map(|r| r.name), Some("game".to_string()));
+        assert_eq!(call.name.name, "run");
+        assert_eq!(call.arguments.len(), 2);
+        assert!(matches!(call.arguments[1].term, Term::ArrayAccess { .. }));
+        assert_eq!((call.span.start, call.span.end), (0, 17));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compile_term_when_identifier() -> Result<()> {
+        let mut compilation_engine = compilation_engine("a[i] f(x) C.g() b;")?;
+        assert!(matches!(
+            compilation_engine.compile_term()?,
+            Term::ArrayAccess { .. }
+        ));
+        let Term::SubroutineCall(call) = compilation_engine.compile_term()? else {
+            panic!("term is not subroutine call");
+        };
+        assert_eq!((call.receiver, call.name.name.as_str()), (None, "f"));
+        let Term::SubroutineCall(call) = compilation_engine.compile_term()? else {
+            panic!("term is not subroutine call");
+        };
+        assert_eq!(call.receiver.map(|r| r.name), Some("C".to_string()));
+        assert!(matches!(
+            compilation_engine.compile_term()?,
+            Term::VarName(Identifier { name, .. }) if name == "b"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compile_class() -> Result<()> {
+        let jack_code = "class Main {
+    field int x, y;
+    method void run() {
+        var Array a;
+        let a[0] = -x + 1;
+        if (x < y) { do Output.printInt(x); } else { return; }
+        return;
+    }
+}";
+        let tokenizer = JackTokenizer::new(Cursor::new(jack_code))?;
+        let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+        let class = compilation_engine.compile_class()?;
 
-        assert_eq!(expect, actual);
+        assert_eq!(class.name.name, "Main");
+        assert_eq!(class.class_var_decs[0].names.len(), 2);
+        let subroutine = &class.subroutine_decs[0];
+        assert_eq!(subroutine.name.name, "run");
+        assert_eq!(subroutine.body.var_decs[0].var_type.name(), "Array");
+        assert!(matches!(
+            subroutine.body.statements.as_slice(),
+            [Statement::Let(_), Statement::If(_), Statement::Return(_)]
+        ));
+        assert_eq!((class.span.start, class.span.end), (0, jack_code.len()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compile_class_populates_symbol_table() -> Result<()> {
+        let jack_code = "class Point {
+    static int count;
+    field int x, y;
+    method int distance(Point other, int scale) {
+        var int dx, dy;
+        return dx;
+    }
+    function void main() {
+        var Point p;
+        return;
+    }
+}";
+        let tokenizer = JackTokenizer::new(Cursor::new(jack_code))?;
+        let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+        compilation_engine.compile_class()?;
+        let mut symbol_table = compilation_engine.symbol_table().clone();
+
+        assert_eq!(symbol_table.var_count(Kind::Field), 2);
+        assert_eq!(symbol_table.var_count(Kind::Static), 1);
+        assert_eq!(symbol_table.kind_of("p"), Some(Kind::Var));
+        assert_eq!(symbol_table.type_of("p"), Some("Point"));
+        assert_eq!(symbol_table.kind_of("dx"), None);
+
+        assert!(symbol_table.enter_subroutine("distance"));
+        assert_eq!(symbol_table.index_of("this"), Some(0));
+        assert_eq!(symbol_table.type_of("this"), Some("Point"));
+        assert_eq!(symbol_table.index_of("scale"), Some(2));
+        assert_eq!(symbol_table.kind_of("dy"), Some(Kind::Var));
+        assert_eq!(symbol_table.index_of("dy"), Some(1));
+        assert_eq!(symbol_table.index_of("y"), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_syntax_error_has_position() -> Result<()> {
+        let jack_code = Cursor::new("class Main {\n  field int x\n}");
+        let tokenizer = JackTokenizer::new(jack_code)?;
+        let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+        let err = compilation_engine.compile_class().unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "3:1: expected `;` after class variable declaration, found `}`"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_class_reports_multiple_errors() -> Result<()> {
+        let jack_code = Cursor::new(
+            "class Main {
+  field int x
+  function void main() {
+    var int a;
+    let a = ;
+    do Output.printInt(a);
+    let = 1;
+    return;
+  }
+  method void run( {
+    return;
+  }
+  function int one() {
+    return 1;
+  }
+}",
+        );
+        let tokenizer = JackTokenizer::new(jack_code)?;
+        let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+        let (class, diagnostics) = compilation_engine.parse_class();
+        let messages = diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "3:3: expected `;` after class variable declaration, found `function`",
+                "5:13: expected expression in `let` statement, found `;`",
+                "7:9: expected identifier in `let` statement, found `=`",
+                "10:20: expected `)` in subroutine declaration, found `{`",
+            ]
+        );
+        let class = class.unwrap();
+        let subroutine_names = class
+            .subroutine_decs
+            .iter()
+            .map(|s| s.name.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(subroutine_names, vec!["main", "one"]);
+        // エラーのない文は構文木に残る
+        assert_eq!(class.subroutine_decs[0].body.statements.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_class_when_unexpected_end_of_input() -> Result<()> {
+        let jack_code = Cursor::new("class Main {\n  function void main() {\n    let x = 1");
+        let tokenizer = JackTokenizer::new(jack_code)?;
+        let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+        let (class, diagnostics) = compilation_engine.parse_class();
+
+        assert!(class.is_some());
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "3:14: expected `;` after `let` statement, found end of input"
+        );
+        // ";"の不足は直前のトークンの直後への挿入を提案する
+        let suggestion = diagnostics[0].suggestion.as_ref().unwrap();
+        assert_eq!(suggestion.replacement, ";");
+        assert_eq!(
+            (
+                suggestion.span.start,
+                suggestion.span.line,
+                suggestion.span.column
+            ),
+            (51, 3, 14)
+        );
+
+        // 非ASCIIの文字列定数の後でも列は文字数で数える
+        let jack_code =
+            Cursor::new("class Main {\n  function void main() {\n    let s = \"é\"\n  }\n}");
+        let tokenizer = JackTokenizer::new(jack_code)?;
+        let (_, diagnostics) = CompilationEngine::new(tokenizer)?.parse_class();
+        let suggestion = diagnostics[0].suggestion.as_ref().unwrap();
+        assert_eq!(
+            (
+                suggestion.span.start,
+                suggestion.span.line,
+                suggestion.span.column
+            ),
+            (54, 3, 16)
+        );
+
+        // どの位置で途切れても解析が終了する
+        let source = "class Main { field int x; method void run(int a) { var int b; \
+            if (a < 1) { let b = a[0] + (-x); } else { do run(b); } while (~b) { return; } } }";
+        for end in 0..source.len() {
+            let tokenizer = JackTokenizer::new(Cursor::new(&source[..end]))?;
+            let (_, diagnostics) = CompilationEngine::new(tokenizer)?.parse_class();
+            assert!(!diagnostics.is_empty());
+        }
         Ok(())
     }
 }
@@ -0,0 +1,161 @@
+use std::fmt;
+
+use jack_tokenizer::{LexError, Span};
+use strum_macros::AsRefStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+// spanのlineが0の診断はファイル全体に対するもの(入出力エラー等)を表す
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // 診断の種類を表す識別子。lintではルール名を使う
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Span,
+    pub notes: Vec<String>,
+    pub suggestion: Option<Suggestion>,
+}
+
+// spanの範囲をreplacementで置き換える修正案。挿入の場合はstartとendが等しい
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub message: String,
+    pub replacement: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            span,
+            notes: Vec::new(),
+            suggestion: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Error, message, span)
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Warning, message, span)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_suggestion(
+        mut self,
+        message: impl Into<String>,
+        replacement: impl Into<String>,
+        span: Span,
+    ) -> Self {
+        self.suggestion = Some(Suggestion {
+            message: message.into(),
+            replacement: replacement.into(),
+            span,
+        });
+        self
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(e: LexError) -> Self {
+        let code = match &e {
+            LexError::UnterminatedString { .. } => "unterminated-string",
+            LexError::IntegerOutOfRange { .. } => "integer-out-of-range",
+            LexError::IllegalCharacter { .. } => "illegal-character",
+            LexError::UnterminatedBlockComment { .. } => "unterminated-block-comment",
+            LexError::NewlineInString { .. } => "newline-in-string",
+            LexError::Io { .. } => "io-error",
+        };
+        // LexErrorのDisplayは先頭に位置を含むので取り除く
+        let span = e.span();
+        let message = e.to_string();
+        let message = message
+            .strip_prefix(&format!("{}: ", span))
+            .unwrap_or(&message);
+        Diagnostic::error(message, span).with_code(code)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "{}: {}", self.span, self.message),
+            Severity::Warning => write!(f, "{}: warning: {}", self.span, self.message),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+// 1つのファイルに対する複数の診断をまとめたエラー。Displayは1行に1つの診断を出力する
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = self.0.iter().map(ToString::to_string).collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostic_display() {
+        let span = Span {
+            start: 10,
+            end: 11,
+            line: 2,
+            column: 5,
+        };
+
+        assert_eq!(
+            Diagnostic::error("syntax error", span).to_string(),
+            "2:5: syntax error"
+        );
+        assert_eq!(
+            Diagnostic::warning("unused variable", span).to_string(),
+            "2:5: warning: unused variable"
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_from_lex_error() {
+        let span = Span {
+            start: 4,
+            end: 5,
+            line: 1,
+            column: 5,
+        };
+        let diagnostic = Diagnostic::from(LexError::IllegalCharacter {
+            character: '$',
+            span,
+        });
+
+        assert_eq!(diagnostic.code, Some("illegal-character"));
+        assert_eq!(diagnostic.to_string(), "1:5: illegal character: '$'");
+    }
+}
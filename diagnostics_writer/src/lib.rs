@@ -0,0 +1,506 @@
+use anyhow::Result;
+use std::{
+    collections::BTreeSet,
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use diagnostics::{Diagnostic, Severity, Suggestion};
+use jack_tokenizer::Span;
+use serde_json::{json, Value};
+
+const TOOL_NAME: &str = "JackAnalyzer";
+const SARIF_VERSION: &str = "2.1.0";
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+// テキスト形式で端末に出力する場合のANSIエスケープシーケンスの書式
+const ERROR_STYLE: &str = "1;31";
+const WARNING_STYLE: &str = "1;33";
+const GUTTER_STYLE: &str = "1;34";
+const HELP_STYLE: &str = "1;36";
+const BOLD: &str = "1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiagnosticFormat {
+    // ソースの該当行とキャレットを含む人間向けのテキスト
+    #[default]
+    Text,
+    // 1行に1つの診断を書くJSON Lines
+    Json,
+    // すべてのファイルの診断をまとめた1つのSARIF 2.1.0のログ
+    Sarif,
+}
+
+pub struct DiagnosticsWriter {
+    writer: Arc<Mutex<dyn Write>>,
+    format: DiagnosticFormat,
+    // SARIFは1つの文書にまとめるのでfinishまで書き込まない
+    results: Vec<Value>,
+    rule_ids: BTreeSet<&'static str>,
+    // テキスト形式で重大度やキャレットを色付けする
+    color: bool,
+}
+
+impl DiagnosticsWriter {
+    pub fn new(writer: Arc<Mutex<dyn Write>>, format: DiagnosticFormat) -> Self {
+        Self {
+            writer,
+            format,
+            results: Vec::new(),
+            rule_ids: BTreeSet::new(),
+            color: false,
+        }
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    // sourceは該当行の表示と終了位置の計算に使う
+    pub fn write(&mut self, path: &str, source: &str, diagnostics: &[Diagnostic]) -> Result<()> {
+        for diagnostic in diagnostics {
+            match self.format {
+                DiagnosticFormat::Text => self.write_text(path, source, diagnostic)?,
+                DiagnosticFormat::Json => {
+                    let mut writer = self.writer.lock().unwrap();
+                    serde_json::to_writer(
+                        &mut *writer,
+                        &json_diagnostic(path, source, diagnostic),
+                    )?;
+                    writeln!(writer)?;
+                }
+                DiagnosticFormat::Sarif => {
+                    self.rule_ids.extend(diagnostic.code);
+                    self.results.push(sarif_result(path, source, diagnostic));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // SARIFの場合は診断がなくても空のresultsを持つログを書き込む
+    pub fn finish(self) -> Result<()> {
+        if self.format != DiagnosticFormat::Sarif {
+            return Ok(());
+        }
+        let rules = self
+            .rule_ids
+            .iter()
+            .map(|rule_id| json!({ "id": rule_id }))
+            .collect::<Vec<_>>();
+        let log = json!({
+            "$schema": SARIF_SCHEMA,
+            "version": SARIF_VERSION,
+            "runs": [{
+                "tool": { "driver": { "name": TOOL_NAME, "rules": rules } },
+                "results": self.results,
+            }],
+        });
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer_pretty(&mut *writer, &log)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    // error[code]: message
+    //  --> path:line:column
+    //   |
+    // 3 |     let x = y
+    //   |             ^
+    //   = note: ...
+    // help: ...
+    //   |
+    // 3 |     let x = y;
+    //   |              +
+    fn write_text(&mut self, path: &str, source: &str, diagnostic: &Diagnostic) -> Result<()> {
+        let severity_style = match diagnostic.severity {
+            Severity::Error => ERROR_STYLE,
+            Severity::Warning => WARNING_STYLE,
+        };
+        let label = match diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity.as_ref(), code),
+            None => diagnostic.severity.as_ref().to_string(),
+        };
+        let span = diagnostic.span;
+        let max_line = diagnostic
+            .suggestion
+            .as_ref()
+            .map_or(span.line, |suggestion| suggestion.span.line.max(span.line));
+        let gutter = " ".repeat(max_line.to_string().len());
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(
+            writer,
+            "{}{}",
+            self.paint(severity_style, &label),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        )?;
+        let location = match span.line {
+            0 => path.to_string(),
+            _ => format!("{}:{}", path, span),
+        };
+        writeln!(
+            writer,
+            "{}{} {}",
+            gutter,
+            self.paint(GUTTER_STYLE, "-->"),
+            location
+        )?;
+        if let Some((line, marker)) = snippet(source, span) {
+            self.write_source_line(&mut *writer, &gutter, span.line, line)?;
+            writeln!(
+                writer,
+                "{} {} {}",
+                gutter,
+                self.paint(GUTTER_STYLE, "|"),
+                self.paint_marker(severity_style, &marker)
+            )?;
+        }
+        for note in &diagnostic.notes {
+            writeln!(
+                writer,
+                "{} {} {} {}",
+                gutter,
+                self.paint(GUTTER_STYLE, "="),
+                self.paint(BOLD, "note:"),
+                note
+            )?;
+        }
+        if let Some(suggestion) = &diagnostic.suggestion {
+            writeln!(
+                writer,
+                "{} {}",
+                self.paint(HELP_STYLE, "help:"),
+                suggestion.message
+            )?;
+            if let Some((line, marker)) = suggestion_snippet(source, suggestion) {
+                self.write_source_line(&mut *writer, &gutter, suggestion.span.line, &line)?;
+                writeln!(
+                    writer,
+                    "{} {} {}",
+                    gutter,
+                    self.paint(GUTTER_STYLE, "|"),
+                    self.paint_marker(HELP_STYLE, &marker)
+                )?;
+            }
+        }
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    //   |
+    // 3 |     let x = y
+    fn write_source_line(
+        &self,
+        writer: &mut dyn Write,
+        gutter: &str,
+        line_number: usize,
+        line: &str,
+    ) -> Result<()> {
+        writeln!(writer, "{} {}", gutter, self.paint(GUTTER_STYLE, "|"))?;
+        let line_number = format!("{:>width$} |", line_number, width = gutter.len());
+        writeln!(
+            writer,
+            "{} {}",
+            self.paint(GUTTER_STYLE, &line_number),
+            line
+        )?;
+        Ok(())
+    }
+
+    // 先頭の空白は色付けしない
+    fn paint_marker(&self, style: &str, marker: &str) -> String {
+        let mark = marker.trim_start();
+        format!(
+            "{}{}",
+            &marker[..marker.len() - mark.len()],
+            self.paint(style, mark)
+        )
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", style, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+// 行頭からoffsetまでの表示幅の空白。タブはそのまま残して表示上の位置を合わせる
+fn padding(source: &str, line_start: usize, offset: usize) -> String {
+    source[line_start..offset]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+// offsetを含む行の(先頭, 末尾)のバイトオフセット。末尾は改行を含まない
+fn line_bounds(source: &str, offset: usize) -> Option<(usize, usize)> {
+    let line_start = source.get(..offset)?.rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i);
+    Some((line_start, line_end))
+}
+
+// spanの開始行と、その下に表示するキャレット。複数行にまたがる場合は開始行の末尾までを示す
+fn snippet(source: &str, span: Span) -> Option<(&str, String)> {
+    if span.line == 0 {
+        return None;
+    }
+    let (line_start, line_end) = line_bounds(source, span.start)?;
+    let line = source[line_start..line_end].trim_end_matches('\r');
+    let width = source
+        .get(span.start..span.end.clamp(span.start, line_end))
+        .map_or(0, |text| text.chars().count());
+    Some((
+        line,
+        format!(
+            "{}{}",
+            padding(source, line_start, span.start),
+            "^".repeat(width.max(1))
+        ),
+    ))
+}
+
+// 修正案を適用した行と、置き換えた位置を示す印。挿入は"+"、置換は"~"で示す
+fn suggestion_snippet(source: &str, suggestion: &Suggestion) -> Option<(String, String)> {
+    let span = suggestion.span;
+    if span.line == 0 {
+        return None;
+    }
+    let (line_start, line_end) = line_bounds(source, span.start)?;
+    let after = source.get(span.end..line_end)?.trim_end_matches('\r');
+    let line = format!(
+        "{}{}{}",
+        &source[line_start..span.start],
+        suggestion.replacement,
+        after
+    );
+    let mark = if span.start == span.end { "+" } else { "~" };
+    let marker = format!(
+        "{}{}",
+        padding(source, line_start, span.start),
+        mark.repeat(suggestion.replacement.chars().count().max(1))
+    );
+    Some((line, marker))
+}
+
+// spanの終端の(行, 列)。列はSARIFと同じく最後の文字の次を指す
+fn end_position(source: &str, span: Span) -> (usize, usize) {
+    let Some(text) = source.get(span.start..span.end) else {
+        return (span.line, span.column);
+    };
+    match text.rfind('\n') {
+        Some(i) => (
+            span.line + text.matches('\n').count(),
+            text[i + 1..].chars().count() + 1,
+        ),
+        None => (span.line, span.column + text.chars().count()),
+    }
+}
+
+fn json_span(source: &str, span: Span) -> Value {
+    if span.line == 0 {
+        return Value::Null;
+    }
+    let (end_line, end_column) = end_position(source, span);
+    json!({
+        "start": span.start,
+        "end": span.end,
+        "line": span.line,
+        "column": span.column,
+        "end_line": end_line,
+        "end_column": end_column,
+    })
+}
+
+fn json_diagnostic(path: &str, source: &str, diagnostic: &Diagnostic) -> Value {
+    json!({
+        "file": path,
+        "code": diagnostic.code,
+        "severity": diagnostic.severity.as_ref(),
+        "message": diagnostic.message,
+        "span": json_span(source, diagnostic.span),
+        "notes": diagnostic.notes,
+        "suggestion": diagnostic.suggestion.as_ref().map(|Suggestion { message, replacement, span }| {
+            json!({
+                "message": message,
+                "replacement": replacement,
+                "span": json_span(source, *span),
+            })
+        }),
+    })
+}
+
+fn sarif_region(source: &str, span: Span) -> Value {
+    let (end_line, end_column) = end_position(source, span);
+    json!({
+        "startLine": span.line,
+        "startColumn": span.column,
+        "endLine": end_line,
+        "endColumn": end_column,
+    })
+}
+
+fn sarif_result(path: &str, source: &str, diagnostic: &Diagnostic) -> Value {
+    // 注記はコードレビューのツールで表示されるようにメッセージの後に続ける
+    let text = std::iter::once(diagnostic.message.clone())
+        .chain(
+            diagnostic
+                .notes
+                .iter()
+                .map(|note| format!("note: {}", note)),
+        )
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut physical_location = json!({ "artifactLocation": { "uri": path } });
+    if diagnostic.span.line != 0 {
+        physical_location["region"] = sarif_region(source, diagnostic.span);
+    }
+    let mut result = json!({
+        "level": diagnostic.severity.as_ref(),
+        "message": { "text": text },
+        "locations": [{ "physicalLocation": physical_location }],
+    });
+    if let Some(code) = diagnostic.code {
+        result["ruleId"] = json!(code);
+    }
+    if let Some(suggestion) = &diagnostic.suggestion {
+        result["fixes"] = json!([{
+            "description": { "text": suggestion.message },
+            "artifactChanges": [{
+                "artifactLocation": { "uri": path },
+                "replacements": [{
+                    "deletedRegion": sarif_region(source, suggestion.span),
+                    "insertedContent": { "text": suggestion.replacement },
+                }],
+            }],
+        }]);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    const SOURCE: &str = "class Main {\n\tfunction void main() {\n\t\tdo game.best();\n\t}\n}\n";
+
+    // 3行目の"game"に対する診断
+    fn diagnostic() -> Diagnostic {
+        let span = Span {
+            start: 42,
+            end: 46,
+            line: 3,
+            column: 6,
+        };
+        Diagnostic::error("function called as a method: \"Game.best\"", span)
+            .with_code("function-as-method")
+            .with_note("\"game\" is a \"Game\"")
+            .with_suggestion("call it on the class \"Game\"", "Game", span)
+    }
+
+    fn write(format: DiagnosticFormat, diagnostics: &[Diagnostic]) -> Result<String> {
+        let buffer = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+        let mut writer = DiagnosticsWriter::new(buffer.clone(), format);
+        writer.write("Main.jack", SOURCE, diagnostics)?;
+        writer.finish()?;
+        let content = buffer.lock().unwrap().get_ref().clone();
+        Ok(String::from_utf8(content)?)
+    }
+
+    #[test]
+    fn test_write_text() -> Result<()> {
+        let warning = Diagnostic::warning("file is not formatted", Span::default());
+
+        assert_eq!(
+            write(DiagnosticFormat::Text, &[diagnostic(), warning])?,
+            "error[function-as-method]: function called as a method: \"Game.best\"
+ --> Main.jack:3:6
+  |
+3 | \t\tdo game.best();
+  | \t\t   ^^^^
+  = note: \"game\" is a \"Game\"
+help: call it on the class \"Game\"
+  |
+3 | \t\tdo Game.best();
+  | \t\t   ~~~~
+
+warning: file is not formatted
+ --> Main.jack
+
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_text_with_color() -> Result<()> {
+        let buffer = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+        DiagnosticsWriter::new(buffer.clone(), DiagnosticFormat::Text)
+            .with_color(true)
+            .write("Main.jack", SOURCE, &[diagnostic()])?;
+        let output = String::from_utf8(buffer.lock().unwrap().get_ref().clone())?;
+
+        assert!(output.starts_with(
+            "\x1b[1;31merror[function-as-method]\x1b[0m\x1b[1m: function called as a method"
+        ));
+        assert!(output.contains("\x1b[1;31m^^^^\x1b[0m"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_json() -> Result<()> {
+        let output = write(DiagnosticFormat::Json, &[diagnostic(), diagnostic()])?;
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+
+        let value: Value = serde_json::from_str(lines[0])?;
+        assert_eq!(value["file"], "Main.jack");
+        assert_eq!(value["code"], "function-as-method");
+        assert_eq!(value["severity"], "error");
+        assert_eq!(
+            value["span"],
+            json!({"start": 42, "end": 46, "line": 3, "column": 6, "end_line": 3, "end_column": 10})
+        );
+        assert_eq!(value["notes"], json!(["\"game\" is a \"Game\""]));
+        assert_eq!(value["suggestion"]["replacement"], "Game");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_sarif() -> Result<()> {
+        let value: Value = serde_json::from_str(&write(DiagnosticFormat::Sarif, &[diagnostic()])?)?;
+        assert_eq!(value["version"], "2.1.0");
+        let run = &value["runs"][0];
+        assert_eq!(
+            run["tool"]["driver"]["rules"],
+            json!([{"id": "function-as-method"}])
+        );
+
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "function-as-method");
+        assert_eq!(result["level"], "error");
+        assert_eq!(
+            result["message"]["text"],
+            "function called as a method: \"Game.best\"\nnote: \"game\" is a \"Game\""
+        );
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "Main.jack");
+        assert_eq!(
+            location["region"],
+            json!({"startLine": 3, "startColumn": 6, "endLine": 3, "endColumn": 10})
+        );
+        let replacement = &result["fixes"][0]["artifactChanges"][0]["replacements"][0];
+        assert_eq!(replacement["insertedContent"]["text"], "Game");
+
+        // 診断がなくても空のログを書き込む
+        let value: Value = serde_json::from_str(&write(DiagnosticFormat::Sarif, &[])?)?;
+        assert_eq!(value["runs"][0]["results"], json!([]));
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use jack_tokenizer::Span;
+use strum_macros::{AsRefStr, EnumString};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identifier {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    pub name: Identifier,
+    pub class_var_decs: Vec<ClassVarDec>,
+    pub subroutine_decs: Vec<SubroutineDec>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum ClassVarKind {
+    Static,
+    Field,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassVarDec {
+    pub kind: ClassVarKind,
+    pub var_type: Type,
+    pub names: Vec<Identifier>,
+    pub span: Span,
+}
+
+// type -> "int"|"char"|"boolean"|className
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Char,
+    Boolean,
+    ClassName(Identifier),
+}
+
+impl Type {
+    pub fn name(&self) -> &str {
+        match self {
+            Type::Int => "int",
+            Type::Char => "char",
+            Type::Boolean => "boolean",
+            Type::ClassName(identifier) => &identifier.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReturnType {
+    Void,
+    Type(Type),
+}
+
+impl ReturnType {
+    pub fn name(&self) -> &str {
+        match self {
+            ReturnType::Void => "void",
+            ReturnType::Type(t) => t.name(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum SubroutineKind {
+    Constructor,
+    Function,
+    Method,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubroutineDec {
+    pub kind: SubroutineKind,
+    pub return_type: ReturnType,
+    pub name: Identifier,
+    pub parameters: Vec<Parameter>,
+    pub body: SubroutineBody,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub var_type: Type,
+    pub name: Identifier,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubroutineBody {
+    pub var_decs: Vec<VarDec>,
+    pub statements: Vec<Statement>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDec {
+    pub var_type: Type,
+    pub names: Vec<Identifier>,
+    pub span: Span,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Let(LetStatement),
+    If(IfStatement),
+    While(WhileStatement),
+    Do(DoStatement),
+    Return(ReturnStatement),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Let(s) => s.span,
+            Statement::If(s) => s.span,
+            Statement::While(s) => s.span,
+            Statement::Do(s) => s.span,
+            Statement::Return(s) => s.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LetStatement {
+    pub name: Identifier,
+    pub index: Option<Expression>,
+    pub value: Expression,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IfStatement {
+    pub condition: Expression,
+    pub then_statements: Vec<Statement>,
+    pub else_statements: Option<Vec<Statement>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhileStatement {
+    pub condition: Expression,
+    pub statements: Vec<Statement>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoStatement {
+    pub call: SubroutineCall,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnStatement {
+    pub value: Option<Expression>,
+    pub span: Span,
+}
+
+// expression -> term (op term)*
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub term: Term,
+    pub rest: Vec<(BinaryOp, Term)>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    And,
+    Or,
+    Lt,
+    Gt,
+    Eq,
+}
+
+impl BinaryOp {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "+" => Some(BinaryOp::Add),
+            "-" => Some(BinaryOp::Sub),
+            "*" => Some(BinaryOp::Mul),
+            "/" => Some(BinaryOp::Div),
+            "&" => Some(BinaryOp::And),
+            "|" => Some(BinaryOp::Or),
+            "<" => Some(BinaryOp::Lt),
+            ">" => Some(BinaryOp::Gt),
+            "=" => Some(BinaryOp::Eq),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::And => "&",
+            BinaryOp::Or => "|",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::Eq => "=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "-" => Some(UnaryOp::Neg),
+            "~" => Some(UnaryOp::Not),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "~",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum KeywordConstant {
+    True,
+    False,
+    Null,
+    This,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    IntegerConstant {
+        value: u16,
+        span: Span,
+    },
+    StringConstant {
+        value: String,
+        span: Span,
+    },
+    KeywordConstant {
+        keyword: KeywordConstant,
+        span: Span,
+    },
+    VarName(Identifier),
+    ArrayAccess {
+        name: Identifier,
+        index: Box<Expression>,
+        span: Span,
+    },
+    SubroutineCall(SubroutineCall),
+    Parenthesized {
+        expression: Box<Expression>,
+        span: Span,
+    },
+    UnaryOp {
+        op: UnaryOp,
+        term: Box<Term>,
+        span: Span,
+    },
+}
+
+impl Term {
+    pub fn span(&self) -> Span {
+        match self {
+            Term::IntegerConstant { span, .. }
+            | Term::StringConstant { span, .. }
+            | Term::KeywordConstant { span, .. }
+            | Term::ArrayAccess { span, .. }
+            | Term::Parenthesized { span, .. }
+            | Term::UnaryOp { span, .. } => *span,
+            Term::VarName(identifier) => identifier.span,
+            Term::SubroutineCall(call) => call.span,
+        }
+    }
+}
+
+// 文字列定数に書けるJackの文字セットの文字(印字可能なASCII)か。
+// VMコードではString.appendCharに1文字ずつ文字コードを渡す
+pub fn is_jack_character(c: char) -> bool {
+    matches!(c, ' '..='~')
+}
+
+// subroutineCall -> subroutineName '(' expressionList ')'
+//                 | (className|varName) '.' subroutineName '(' expressionList ')'
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubroutineCall {
+    pub receiver: Option<Identifier>,
+    pub name: Identifier,
+    pub arguments: Vec<Expression>,
+    pub span: Span,
+}
@@ -0,0 +1,541 @@
+use anyhow::Result;
+use compilation_engine::CompilationEngine;
+use jack_ast::{
+    Class, ClassVarDec, Expression, Statement, SubroutineCall, SubroutineDec, Term, VarDec,
+};
+use jack_tokenizer::{
+    tokenize_with_trivia, JackTokenizer, KeyWord, Span, Token, Trivia, TriviaKind,
+};
+
+const INDENT: &str = "    ";
+
+// ソースを構文解析してから整形する。構文エラーがある場合は整形しない
+pub fn format_source(source: &str) -> Result<String> {
+    let tokenizer = JackTokenizer::new(source.as_bytes())?;
+    let class = CompilationEngine::new(tokenizer)?.compile_class()?;
+    Ok(JackFormatter::new(source)?.format(&class))
+}
+
+#[derive(Debug, Clone)]
+struct Comment {
+    text: String,
+    start: usize,
+    line: usize,
+    column: usize,
+    end_line: usize,
+}
+
+impl Comment {
+    fn new(trivia: &Trivia) -> Option<Self> {
+        if trivia.kind == TriviaKind::Whitespace {
+            return None;
+        }
+        Some(Self {
+            text: trivia.text.trim_end().to_string(),
+            start: trivia.span.start,
+            line: trivia.span.line,
+            column: trivia.span.column,
+            end_line: trivia.span.line + trivia.text.trim_end().matches('\n').count(),
+        })
+    }
+}
+
+pub struct JackFormatter {
+    comments: Vec<Comment>,
+    next_comment: usize,
+    // elseの位置はASTに残らないのでトークンから求める
+    else_offsets: Vec<usize>,
+    // 行末のコメントの判定と、ASTに位置が残らない"{"の位置に使う
+    token_spans: Vec<Span>,
+    line_starts: Vec<usize>,
+    output: String,
+    depth: usize,
+    // 最後に出力した要素のソース上の末尾の行。空行を保つかの判定に使う
+    last_line: usize,
+    // ブロックの先頭では空行を入れない
+    at_block_start: bool,
+    // サブルーチンの前には必ず空行を1行入れる
+    force_blank_line: bool,
+}
+
+impl JackFormatter {
+    pub fn new(source: &str) -> Result<Self> {
+        let trivia_tokens = tokenize_with_trivia(source)?;
+        let mut comments = Vec::new();
+        for token in &trivia_tokens.tokens {
+            comments.extend(token.leading_trivia.iter().filter_map(Comment::new));
+            comments.extend(token.trailing_trivia.iter().filter_map(Comment::new));
+        }
+        comments.extend(
+            trivia_tokens
+                .end_of_file_trivia
+                .iter()
+                .filter_map(Comment::new),
+        );
+        let else_offsets = trivia_tokens
+            .tokens
+            .iter()
+            .filter(|token| token.lexeme.token == Token::Keyword(KeyWord::Else))
+            .map(|token| token.lexeme.span.start)
+            .collect();
+        let token_spans = trivia_tokens
+            .tokens
+            .iter()
+            .map(|token| token.lexeme.span)
+            .collect();
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Ok(Self {
+            comments,
+            next_comment: 0,
+            else_offsets,
+            token_spans,
+            line_starts,
+            output: String::new(),
+            depth: 0,
+            last_line: 0,
+            at_block_start: true,
+            force_blank_line: false,
+        })
+    }
+
+    pub fn format(mut self, class: &Class) -> String {
+        self.flush_comments(class.span.start);
+        self.begin_element(class.span.line);
+        let header_end = self.token_end(class.name.span.end, 0);
+        self.line(&format!("class {} {{", class.name.name), header_end);
+        self.open_block();
+        for class_var_dec in &class.class_var_decs {
+            self.class_var_dec(class_var_dec);
+        }
+        for subroutine_dec in &class.subroutine_decs {
+            self.force_blank_line = true;
+            self.flush_comments(subroutine_dec.span.start);
+            self.subroutine_dec(subroutine_dec);
+        }
+        self.close_block("}", class.span.end - 1);
+        // ファイル末尾のコメント
+        self.flush_comments(usize::MAX);
+        self.output
+    }
+
+    fn class_var_dec(&mut self, class_var_dec: &ClassVarDec) {
+        self.flush_comments(class_var_dec.span.start);
+        self.begin_element(class_var_dec.span.line);
+        let names = class_var_dec
+            .names
+            .iter()
+            .map(|name| name.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.line(
+            &format!(
+                "{} {} {};",
+                class_var_dec.kind.as_ref(),
+                class_var_dec.var_type.name(),
+                names
+            ),
+            class_var_dec.span.end,
+        );
+    }
+
+    fn subroutine_dec(&mut self, subroutine_dec: &SubroutineDec) {
+        self.begin_element(subroutine_dec.span.line);
+        let parameters = subroutine_dec
+            .parameters
+            .iter()
+            .map(|parameter| format!("{} {}", parameter.var_type.name(), parameter.name.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.line(
+            &format!(
+                "{} {} {}({}) {{",
+                subroutine_dec.kind.as_ref(),
+                subroutine_dec.return_type.name(),
+                subroutine_dec.name.name,
+                parameters
+            ),
+            self.token_end(subroutine_dec.body.span.start, 0),
+        );
+        self.open_block();
+        for var_dec in &subroutine_dec.body.var_decs {
+            self.var_dec(var_dec);
+        }
+        self.statements(&subroutine_dec.body.statements);
+        self.close_block("}", subroutine_dec.body.span.end - 1);
+    }
+
+    fn var_dec(&mut self, var_dec: &VarDec) {
+        self.flush_comments(var_dec.span.start);
+        self.begin_element(var_dec.span.line);
+        let names = var_dec
+            .names
+            .iter()
+            .map(|name| name.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.line(
+            &format!("var {} {};", var_dec.var_type.name(), names),
+            var_dec.span.end,
+        );
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            let span = statement.span();
+            self.flush_comments(span.start);
+            self.begin_element(span.line);
+            match statement {
+                Statement::Let(statement) => {
+                    let index = statement
+                        .index
+                        .as_ref()
+                        .map(|index| format!("[{}]", expression(index)))
+                        .unwrap_or_default();
+                    self.line(
+                        &format!(
+                            "let {}{} = {};",
+                            statement.name.name,
+                            index,
+                            expression(&statement.value)
+                        ),
+                        span.end,
+                    );
+                }
+                Statement::If(statement) => {
+                    self.line(
+                        &format!("if ({}) {{", expression(&statement.condition)),
+                        self.token_end(statement.condition.span.end, 1),
+                    );
+                    self.open_block();
+                    self.statements(&statement.then_statements);
+                    if let Some(else_statements) = &statement.else_statements {
+                        let then_end = statement
+                            .then_statements
+                            .last()
+                            .map_or(statement.condition.span.end, |statement| {
+                                statement.span().end
+                            });
+                        let else_offset = self.else_offsets[self
+                            .else_offsets
+                            .partition_point(|&offset| offset < then_end)];
+                        self.close_block("} else {", else_offset);
+                        self.open_block();
+                        self.statements(else_statements);
+                    }
+                    self.close_block("}", span.end - 1);
+                }
+                Statement::While(statement) => {
+                    self.line(
+                        &format!("while ({}) {{", expression(&statement.condition)),
+                        self.token_end(statement.condition.span.end, 1),
+                    );
+                    self.open_block();
+                    self.statements(&statement.statements);
+                    self.close_block("}", span.end - 1);
+                }
+                Statement::Do(statement) => {
+                    self.line(
+                        &format!("do {};", subroutine_call(&statement.call)),
+                        span.end,
+                    );
+                }
+                Statement::Return(statement) => {
+                    let text = match &statement.value {
+                        Some(value) => format!("return {};", expression(value)),
+                        None => "return;".to_string(),
+                    };
+                    self.line(&text, span.end);
+                }
+            }
+        }
+    }
+
+    fn open_block(&mut self) {
+        self.depth += 1;
+        self.at_block_start = true;
+    }
+
+    // startは閉じ括弧のオフセット。それより前のコメントはブロックの内側に出力する。
+    // "} else {"の場合は続く"{"までを1行に出力する
+    fn close_block(&mut self, text: &str, start: usize) {
+        self.flush_comments(start);
+        self.depth -= 1;
+        self.at_block_start = false;
+        self.force_blank_line = false;
+        let skip = if text.ends_with('{') { 1 } else { 0 };
+        let end = self.token_end(start, skip);
+        self.line(text, end);
+    }
+
+    fn begin_element(&mut self, line: usize) {
+        if !self.at_block_start && (self.force_blank_line || line > self.last_line + 1) {
+            self.output.push('\n');
+        }
+        self.at_block_start = false;
+        self.force_blank_line = false;
+    }
+
+    // endは1行に出力する要素のソース上の末尾のオフセット。
+    // 要素の途中にあるコメントは要素の前の行に、要素の直後から次のトークンまでの同じ行のコメントは行末に出力する
+    fn line(&mut self, text: &str, end: usize) {
+        while let Some(comment) = self.next_comment_before(end) {
+            self.comment(&comment);
+        }
+        self.output.push_str(&INDENT.repeat(self.depth));
+        self.output.push_str(text);
+        let source_line = self.line_starts.partition_point(|&start| start < end);
+        let next_token = self
+            .token_spans
+            .get(self.token_index(end))
+            .map_or(usize::MAX, |span| span.start);
+        while let Some(comment) = self
+            .next_comment_before(next_token)
+            .filter(|comment| comment.line == source_line)
+        {
+            self.output.push(' ');
+            self.output.push_str(&comment.text);
+            self.last_line = comment.end_line;
+            self.next_comment += 1;
+        }
+        self.last_line = self.last_line.max(source_line);
+        self.output.push('\n');
+    }
+
+    // limitより前にある未出力のコメントを1行ずつ出力する
+    fn flush_comments(&mut self, limit: usize) {
+        while let Some(comment) = self.next_comment_before(limit) {
+            self.begin_element(comment.line);
+            self.comment(&comment);
+        }
+    }
+
+    fn next_comment_before(&self, limit: usize) -> Option<Comment> {
+        self.comments
+            .get(self.next_comment)
+            .filter(|comment| comment.start < limit)
+            .cloned()
+    }
+
+    // 次のコメントを独立した行として出力する
+    fn comment(&mut self, comment: &Comment) {
+        let indent = INDENT.repeat(self.depth);
+        for (i, text) in comment.text.lines().enumerate() {
+            let text = if i == 0 {
+                text
+            } else {
+                // 複数行のブロックコメントは開始位置からの相対的なインデントを保つ
+                let strip = text
+                    .char_indices()
+                    .take(comment.column - 1)
+                    .take_while(|(_, c)| c.is_whitespace())
+                    .last()
+                    .map_or(0, |(i, c)| i + c.len_utf8());
+                text[strip..].trim_end()
+            };
+            if !text.is_empty() {
+                self.output.push_str(&indent);
+                self.output.push_str(text);
+            }
+            self.output.push('\n');
+        }
+        self.last_line = comment.end_line;
+        self.next_comment += 1;
+    }
+
+    // offset以降で最初に始まるトークンの位置
+    fn token_index(&self, offset: usize) -> usize {
+        self.token_spans.partition_point(|span| span.start < offset)
+    }
+
+    // offset以降のskip+1番目のトークンの末尾。"{"のようにASTに位置が残らないトークンに使う
+    fn token_end(&self, offset: usize, skip: usize) -> usize {
+        self.token_spans
+            .get(self.token_index(offset) + skip)
+            .map_or(offset, |span| span.end)
+    }
+}
+
+fn expression(expression: &Expression) -> String {
+    let mut text = term(&expression.term);
+    for (op, rest) in &expression.rest {
+        text.push_str(&format!(" {} {}", op.symbol(), term(rest)));
+    }
+    text
+}
+
+fn term(node: &Term) -> String {
+    match node {
+        Term::IntegerConstant { value, .. } => value.to_string(),
+        Term::StringConstant { value, .. } => format!("\"{}\"", value),
+        Term::KeywordConstant { keyword, .. } => keyword.as_ref().to_string(),
+        Term::VarName(identifier) => identifier.name.clone(),
+        Term::ArrayAccess { name, index, .. } => format!("{}[{}]", name.name, expression(index)),
+        Term::SubroutineCall(call) => subroutine_call(call),
+        Term::Parenthesized { expression: e, .. } => format!("({})", expression(e)),
+        Term::UnaryOp { op, term: t, .. } => format!("{}{}", op.symbol(), term(t)),
+    }
+}
+
+fn subroutine_call(call: &SubroutineCall) -> String {
+    let arguments = call
+        .arguments
+        .iter()
+        .map(expression)
+        .collect::<Vec<_>>()
+        .join(", ");
+    match &call.receiver {
+        Some(receiver) => format!("{}.{}({})", receiver.name, call.name.name, arguments),
+        None => format!("{}({})", call.name.name, arguments),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    const TEST_JACK_DIR: &str = "../test_files";
+
+    fn comments(source: &str) -> Result<Vec<String>> {
+        Ok(JackFormatter::new(source)?
+            .comments
+            .into_iter()
+            .map(|comment| {
+                comment
+                    .text
+                    .lines()
+                    .map(str::trim)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_format_source() -> Result<()> {
+        let source = r#"// header
+
+class Main{
+field int x,y;   // position
+  static boolean flag;
+    function void main(){var int i;
+    let i=-1+(2*x);
+        if(i<0){do Output.printString("neg");}else{
+        // nothing
+    }
+
+
+        while(~flag){let a[i]=Main.f(i,x);}
+    return;}
+    method int get() { return x; }
+    /** end */
+}
+"#;
+        let expected = r#"// header
+
+class Main {
+    field int x, y; // position
+    static boolean flag;
+
+    function void main() {
+        var int i;
+        let i = -1 + (2 * x);
+        if (i < 0) {
+            do Output.printString("neg");
+        } else {
+            // nothing
+        }
+
+        while (~flag) {
+            let a[i] = Main.f(i, x);
+        }
+        return;
+    }
+
+    method int get() {
+        return x;
+    }
+    /** end */
+}
+"#;
+        assert_eq!(format_source(source)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_source_keeps_comments_after_code() -> Result<()> {
+        // 要素の途中のコメントは前の行に移し、後ろのコメントは前に移さない
+        let source = "class M { function void f() { do g(/* arg */ 1, 2); return; } /* tail */ }";
+        let expected = "class M {
+    function void f() {
+        /* arg */
+        do g(1, 2);
+        return;
+    } /* tail */
+}
+";
+        assert_eq!(format_source(source)?, expected);
+
+        // 複数行の文の行末のコメントは文の末尾に残す
+        let source = "class M {
+    function void f() {
+        var int x;
+        let x = 1 + // c1
+            2; // c2
+        if (x) { // open
+            return; } else { // else
+        }
+        return;
+    }
+}
+";
+        let expected = "class M {
+    function void f() {
+        var int x;
+        // c1
+        let x = 1 + 2; // c2
+        if (x) { // open
+            return;
+        } else { // else
+        }
+        return;
+    }
+}
+";
+        let formatted = format_source(source)?;
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted)?, formatted);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_source_when_syntax_error() {
+        assert!(format_source("class Main { field int; }").is_err());
+    }
+
+    #[test]
+    fn test_format_source_is_idempotent() -> Result<()> {
+        for program in ["ArrayTest", "Square", "ExpressionLessSquare"] {
+            for entry in fs::read_dir(format!("{}/{}", TEST_JACK_DIR, program))? {
+                let path = entry?.path();
+                if path.extension().is_none_or(|e| e != "jack") {
+                    continue;
+                }
+                let source = fs::read_to_string(&path)?;
+                let formatted = format_source(&source)?;
+                assert_eq!(format_source(&formatted)?, formatted, "{}", path.display());
+                // コメントは内容と順序を保ったまま残る
+                assert_eq!(
+                    comments(&formatted)?,
+                    comments(&source)?,
+                    "{}",
+                    path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+}
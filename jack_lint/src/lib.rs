@@ -0,0 +1,744 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use diagnostics::{Diagnostic, Severity};
+use jack_ast::{
+    Class, Expression, Identifier, KeywordConstant, Statement, SubroutineDec, SubroutineKind, Term,
+};
+use jack_tokenizer::{tokenize_with_trivia, Span, TriviaKind};
+use strum_macros::{AsRefStr, EnumString};
+
+// 解析するディレクトリ直下に置くlintの設定ファイル
+pub const CONFIG_FILE: &str = ".jacklint";
+// "// jack-lint: allow(rule, ...)"の形式でコメントの行と次の行の指摘を抑制する
+const ALLOW_DIRECTIVE: &str = "jack-lint: allow(";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum Level {
+    Allow,
+    Warn,
+    Error,
+}
+
+// ルールが検出した問題。重大度はルールの既定値と設定から決まる
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Lint {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+pub trait Rule: Send + Sync {
+    // 設定ファイルとallowコメントで指定する名前
+    fn name(&self) -> &'static str;
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint>;
+}
+
+// ルール名ごとの重大度
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+    levels: HashMap<String, Level>,
+}
+
+impl LintConfig {
+    // 1行に1つ"rule = allow|warn|error"を書く。空行と#で始まる行は無視する
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut config = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((rule, level)) = line.split_once('=') else {
+                return Err(anyhow!("{}: expected \"rule = level\": {:?}", i + 1, line));
+            };
+            let level = level
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("{}: unknown lint level: {:?}", i + 1, level.trim()))?;
+            config.set(rule.trim(), level);
+        }
+        Ok(config)
+    }
+
+    pub fn set(&mut self, rule: &str, level: Level) {
+        self.levels.insert(rule.to_string(), level);
+    }
+}
+
+pub struct Linter {
+    rules: Vec<Box<dyn Rule>>,
+    config: LintConfig,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linter {
+    // 組み込みのルールをすべて登録する
+    pub fn new() -> Self {
+        Self {
+            rules: vec![
+                Box::new(UnusedVariable),
+                Box::new(UnusedParameter),
+                Box::new(UnusedClassVar),
+                Box::new(UnreachableCode),
+                Box::new(MissingReturn),
+                Box::new(ConstructorReturnThis),
+                Box::new(InfiniteLoop),
+                Box::new(EmptyIf),
+                Box::new(ClassNaming),
+                Box::new(SubroutineNaming),
+            ],
+            config: LintConfig::default(),
+        }
+    }
+
+    pub fn register(&mut self, rule: Box<dyn Rule>) {
+        self.rules.push(rule);
+    }
+
+    // 登録されていないルール名が設定にあればエラーにする
+    pub fn with_config(mut self, config: LintConfig) -> Result<Self> {
+        if let Some(rule) = config
+            .levels
+            .keys()
+            .find(|rule| !self.rule_names().contains(&rule.as_str()))
+        {
+            return Err(anyhow!("unknown lint rule: {:?}", rule));
+        }
+        self.config = config;
+        Ok(self)
+    }
+
+    pub fn rule_names(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|rule| rule.name()).collect()
+    }
+
+    // 指摘はソース上の出現順に返す。診断のcodeにはルール名を使う
+    pub fn lint(&self, source: &str, class: &Class) -> Result<Vec<Diagnostic>> {
+        let allowed = allowed_rules(source)?;
+        let mut diagnostics = Vec::new();
+        for rule in &self.rules {
+            let level = self
+                .config
+                .levels
+                .get(rule.name())
+                .copied()
+                .unwrap_or(rule.default_level());
+            for lint in rule.check(class) {
+                if allowed
+                    .get(&lint.span.line)
+                    .is_some_and(|rules| rules.contains(rule.name()))
+                {
+                    continue;
+                }
+                let severity = match level {
+                    Level::Allow => continue,
+                    Level::Warn => Severity::Warning,
+                    Level::Error => Severity::Error,
+                };
+                diagnostics.push(
+                    Diagnostic::new(severity, lint.message, lint.span).with_code(rule.name()),
+                );
+            }
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        Ok(diagnostics)
+    }
+}
+
+// allowコメントを読み取り、行番号ごとに抑制するルール名を返す
+fn allowed_rules(source: &str) -> Result<HashMap<usize, HashSet<String>>> {
+    let trivia_tokens = tokenize_with_trivia(source)?;
+    let mut allowed: HashMap<usize, HashSet<String>> = HashMap::new();
+    let comments = trivia_tokens
+        .tokens
+        .iter()
+        .flat_map(|token| token.leading_trivia.iter().chain(&token.trailing_trivia))
+        .filter(|trivia| trivia.kind != TriviaKind::Whitespace);
+    for comment in comments {
+        let Some((_, rest)) = comment.text.split_once(ALLOW_DIRECTIVE) else {
+            continue;
+        };
+        let Some((rules, _)) = rest.split_once(')') else {
+            continue;
+        };
+        let end_line = comment.span.line + comment.text.trim_end().matches('\n').count();
+        for line in [comment.span.line, end_line + 1] {
+            allowed
+                .entry(line)
+                .or_default()
+                .extend(rules.split(',').map(|rule| rule.trim().to_string()));
+        }
+    }
+    Ok(allowed)
+}
+
+// 値を読み出された変数、配列、呼び出しのレシーバの識別子を集める。
+// 代入するだけの変数は参照とみなさないが、配列の要素への代入は配列自体を読み出す
+fn referenced_names<'a>(statements: &'a [Statement], names: &mut Vec<&'a Identifier>) {
+    for_each_statement(statements, &mut |statement| match statement {
+        Statement::Let(let_statement) => {
+            if let Some(index) = &let_statement.index {
+                names.push(&let_statement.name);
+                expression_names(index, names);
+            }
+            expression_names(&let_statement.value, names);
+        }
+        Statement::If(if_statement) => expression_names(&if_statement.condition, names),
+        Statement::While(while_statement) => expression_names(&while_statement.condition, names),
+        Statement::Do(do_statement) => {
+            names.extend(&do_statement.call.receiver);
+            do_statement
+                .call
+                .arguments
+                .iter()
+                .for_each(|argument| expression_names(argument, names));
+        }
+        Statement::Return(return_statement) => {
+            if let Some(value) = &return_statement.value {
+                expression_names(value, names);
+            }
+        }
+    });
+}
+
+fn expression_names<'a>(expression: &'a Expression, names: &mut Vec<&'a Identifier>) {
+    term_names(&expression.term, names);
+    for (_, term) in &expression.rest {
+        term_names(term, names);
+    }
+}
+
+fn term_names<'a>(term: &'a Term, names: &mut Vec<&'a Identifier>) {
+    match term {
+        Term::VarName(name) => names.push(name),
+        Term::ArrayAccess { name, index, .. } => {
+            names.push(name);
+            expression_names(index, names);
+        }
+        Term::SubroutineCall(call) => {
+            names.extend(&call.receiver);
+            call.arguments
+                .iter()
+                .for_each(|argument| expression_names(argument, names));
+        }
+        Term::Parenthesized { expression, .. } => expression_names(expression, names),
+        Term::UnaryOp { term, .. } => term_names(term, names),
+        Term::IntegerConstant { .. }
+        | Term::StringConstant { .. }
+        | Term::KeywordConstant { .. } => {}
+    }
+}
+
+// if,whileの内側も含めてすべての文を出現順に訪れる
+fn for_each_statement<'a>(statements: &'a [Statement], f: &mut dyn FnMut(&'a Statement)) {
+    for_each_block(statements, &mut |block| block.iter().for_each(&mut *f));
+}
+
+// 文の並び(サブルーチン本体、if,whileのブロック)を外側から順に訪れる
+fn for_each_block<'a>(statements: &'a [Statement], f: &mut dyn FnMut(&'a [Statement])) {
+    f(statements);
+    for statement in statements {
+        match statement {
+            Statement::If(if_statement) => {
+                for_each_block(&if_statement.then_statements, f);
+                if let Some(else_statements) = &if_statement.else_statements {
+                    for_each_block(else_statements, f);
+                }
+            }
+            Statement::While(while_statement) => for_each_block(&while_statement.statements, f),
+            Statement::Let(_) | Statement::Do(_) | Statement::Return(_) => (),
+        }
+    }
+}
+
+fn is_keyword_constant(expression: &Expression, keyword: KeywordConstant) -> bool {
+    expression.rest.is_empty()
+        && matches!(&expression.term, Term::KeywordConstant { keyword: k, .. } if *k == keyword)
+}
+
+// 文の並びの後ろに処理が続かないか。while (true)は抜け出せないので続かないとみなす
+fn always_returns(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Return(_) => true,
+        Statement::If(if_statement) => {
+            always_returns(&if_statement.then_statements)
+                && if_statement
+                    .else_statements
+                    .as_deref()
+                    .is_some_and(always_returns)
+        }
+        Statement::While(while_statement) => {
+            is_keyword_constant(&while_statement.condition, KeywordConstant::True)
+        }
+        Statement::Let(_) | Statement::Do(_) => false,
+    })
+}
+
+// "_"で始まる名前は意図的に使わない変数とみなす
+fn is_unused(name: &Identifier, referenced: &HashSet<&str>) -> bool {
+    !name.name.starts_with('_') && !referenced.contains(name.name.as_str())
+}
+
+fn subroutine_references(subroutine_dec: &SubroutineDec) -> HashSet<&str> {
+    let mut names = Vec::new();
+    referenced_names(&subroutine_dec.body.statements, &mut names);
+    names.iter().map(|name| name.name.as_str()).collect()
+}
+
+pub struct UnusedVariable;
+
+impl Rule for UnusedVariable {
+    fn name(&self) -> &'static str {
+        "unused-variable"
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for subroutine_dec in &class.subroutine_decs {
+            let referenced = subroutine_references(subroutine_dec);
+            for var_dec in &subroutine_dec.body.var_decs {
+                for name in var_dec.names.iter().filter(|n| is_unused(n, &referenced)) {
+                    lints.push(Lint::new(
+                        format!("unused variable: {:?}", name.name),
+                        name.span,
+                    ));
+                }
+            }
+        }
+        lints
+    }
+}
+
+pub struct UnusedParameter;
+
+impl Rule for UnusedParameter {
+    fn name(&self) -> &'static str {
+        "unused-parameter"
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for subroutine_dec in &class.subroutine_decs {
+            let referenced = subroutine_references(subroutine_dec);
+            for parameter in &subroutine_dec.parameters {
+                if is_unused(&parameter.name, &referenced) {
+                    lints.push(Lint::new(
+                        format!("unused parameter: {:?}", parameter.name.name),
+                        parameter.name.span,
+                    ));
+                }
+            }
+        }
+        lints
+    }
+}
+
+// fieldとstaticが対象。ローカル変数や引数に隠された名前の参照は数えない
+pub struct UnusedClassVar;
+
+impl Rule for UnusedClassVar {
+    fn name(&self) -> &'static str {
+        "unused-class-var"
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        let mut referenced = HashSet::new();
+        for subroutine_dec in &class.subroutine_decs {
+            let locals = subroutine_dec
+                .parameters
+                .iter()
+                .map(|parameter| parameter.name.name.as_str())
+                .chain(
+                    subroutine_dec
+                        .body
+                        .var_decs
+                        .iter()
+                        .flat_map(|var_dec| var_dec.names.iter().map(|n| n.name.as_str())),
+                )
+                .collect::<HashSet<_>>();
+            referenced.extend(subroutine_references(subroutine_dec).difference(&locals));
+        }
+        let mut lints = Vec::new();
+        for class_var_dec in &class.class_var_decs {
+            for name in class_var_dec
+                .names
+                .iter()
+                .filter(|n| is_unused(n, &referenced))
+            {
+                lints.push(Lint::new(
+                    format!("unused {}: {:?}", class_var_dec.kind.as_ref(), name.name),
+                    name.span,
+                ));
+            }
+        }
+        lints
+    }
+}
+
+pub struct UnreachableCode;
+
+impl Rule for UnreachableCode {
+    fn name(&self) -> &'static str {
+        "unreachable-code"
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for subroutine_dec in &class.subroutine_decs {
+            for_each_block(&subroutine_dec.body.statements, &mut |block| {
+                // 到達しない文の並びは先頭のみ報告する
+                let unreachable = block
+                    .iter()
+                    .position(|statement| always_returns(std::slice::from_ref(statement)))
+                    .and_then(|i| block.get(i + 1));
+                if let Some(statement) = unreachable {
+                    lints.push(Lint::new("unreachable statement", statement.span()));
+                }
+            });
+        }
+        lints
+    }
+}
+
+// VMコードではreturnのないサブルーチンの末尾から次の関数に処理が続いてしまう
+pub struct MissingReturn;
+
+impl Rule for MissingReturn {
+    fn name(&self) -> &'static str {
+        "missing-return"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Error
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        class
+            .subroutine_decs
+            .iter()
+            .filter(|subroutine_dec| !always_returns(&subroutine_dec.body.statements))
+            .map(|subroutine_dec| {
+                Lint::new(
+                    format!(
+                        "missing return at the end of subroutine: {:?}",
+                        subroutine_dec.name.name
+                    ),
+                    subroutine_dec.name.span,
+                )
+            })
+            .collect()
+    }
+}
+
+pub struct ConstructorReturnThis;
+
+impl Rule for ConstructorReturnThis {
+    fn name(&self) -> &'static str {
+        "constructor-return-this"
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for subroutine_dec in &class.subroutine_decs {
+            if subroutine_dec.kind != SubroutineKind::Constructor {
+                continue;
+            }
+            for_each_statement(&subroutine_dec.body.statements, &mut |statement| {
+                if let Statement::Return(return_statement) = statement {
+                    let returns_this = return_statement
+                        .value
+                        .as_ref()
+                        .is_some_and(|value| is_keyword_constant(value, KeywordConstant::This));
+                    if !returns_this {
+                        lints.push(Lint::new(
+                            "constructor should return this",
+                            return_statement.span,
+                        ));
+                    }
+                }
+            });
+        }
+        lints
+    }
+}
+
+// Jackにはbreakがないので、while (true)から抜けるにはreturnが必要
+pub struct InfiniteLoop;
+
+impl Rule for InfiniteLoop {
+    fn name(&self) -> &'static str {
+        "infinite-loop"
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for subroutine_dec in &class.subroutine_decs {
+            for_each_statement(&subroutine_dec.body.statements, &mut |statement| {
+                let Statement::While(while_statement) = statement else {
+                    return;
+                };
+                if !is_keyword_constant(&while_statement.condition, KeywordConstant::True) {
+                    return;
+                }
+                let mut has_return = false;
+                for_each_statement(&while_statement.statements, &mut |statement| {
+                    has_return |= matches!(statement, Statement::Return(_));
+                });
+                if !has_return {
+                    lints.push(Lint::new(
+                        "while (true) loop has no exit",
+                        while_statement.span,
+                    ));
+                }
+            });
+        }
+        lints
+    }
+}
+
+pub struct EmptyIf;
+
+impl Rule for EmptyIf {
+    fn name(&self) -> &'static str {
+        "empty-if"
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for subroutine_dec in &class.subroutine_decs {
+            for_each_statement(&subroutine_dec.body.statements, &mut |statement| {
+                let Statement::If(if_statement) = statement else {
+                    return;
+                };
+                if if_statement.then_statements.is_empty() {
+                    lints.push(Lint::new("empty if block", if_statement.span));
+                }
+                if if_statement
+                    .else_statements
+                    .as_ref()
+                    .is_some_and(Vec::is_empty)
+                {
+                    lints.push(Lint::new("empty else block", if_statement.span));
+                }
+            });
+        }
+        lints
+    }
+}
+
+pub struct ClassNaming;
+
+impl Rule for ClassNaming {
+    fn name(&self) -> &'static str {
+        "class-naming"
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        let name = &class.name.name;
+        if name.starts_with(|c: char| c.is_ascii_uppercase()) && !name.contains('_') {
+            return Vec::new();
+        }
+        vec![Lint::new(
+            format!("class name should be UpperCamelCase: {:?}", name),
+            class.name.span,
+        )]
+    }
+}
+
+pub struct SubroutineNaming;
+
+impl Rule for SubroutineNaming {
+    fn name(&self) -> &'static str {
+        "subroutine-naming"
+    }
+
+    fn check(&self, class: &Class) -> Vec<Lint> {
+        class
+            .subroutine_decs
+            .iter()
+            .map(|subroutine_dec| &subroutine_dec.name)
+            .filter(|name| {
+                !name.name.starts_with(|c: char| c.is_ascii_lowercase()) || name.name.contains('_')
+            })
+            .map(|name| {
+                Lint::new(
+                    format!("subroutine name should be lowerCamelCase: {:?}", name.name),
+                    name.span,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compilation_engine::CompilationEngine;
+    use jack_tokenizer::JackTokenizer;
+    use pretty_assertions::assert_eq;
+
+    fn lint(linter: &Linter, jack_code: &str) -> Result<Vec<String>> {
+        let tokenizer = JackTokenizer::new(jack_code.as_bytes())?;
+        let class = CompilationEngine::new(tokenizer)?.compile_class()?;
+        Ok(linter
+            .lint(jack_code, &class)?
+            .iter()
+            .map(|diagnostic| format!("{} [{}]", diagnostic, diagnostic.code.unwrap()))
+            .collect())
+    }
+
+    #[test]
+    fn test_lint_when_clean() -> Result<()> {
+        let jack_code = "class Point {
+    field int x;
+    static int count;
+    constructor Point new(int ax) {
+        let x = ax;
+        let count = count + 1;
+        return this;
+    }
+    method int get(int _unused) {
+        while (true) {
+            if (x > 0) { return x; } else { let x = x + 1; }
+        }
+    }
+}";
+
+        assert_eq!(lint(&Linter::new(), jack_code)?, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_rules() -> Result<()> {
+        let jack_code = "class point_t {
+    field int x, y, z;
+    static int count;
+    constructor point_t new(int ax) {
+        var int a, b;
+        let a = x;
+        if (ax) { } else { }
+        return null;
+    }
+    method void Draw() {
+        while (true) { do Output.printInt(y); }
+    }
+    function int get() {
+        return 1;
+        return 2;
+    }
+}";
+
+        assert_eq!(
+            lint(&Linter::new(), jack_code)?,
+            vec![
+                "1:7: warning: class name should be UpperCamelCase: \"point_t\" [class-naming]",
+                "2:21: warning: unused field: \"z\" [unused-class-var]",
+                "3:16: warning: unused static: \"count\" [unused-class-var]",
+                "5:17: warning: unused variable: \"a\" [unused-variable]",
+                "5:20: warning: unused variable: \"b\" [unused-variable]",
+                "7:9: warning: empty if block [empty-if]",
+                "7:9: warning: empty else block [empty-if]",
+                "8:9: warning: constructor should return this [constructor-return-this]",
+                "10:17: warning: subroutine name should be lowerCamelCase: \"Draw\" [subroutine-naming]",
+                "11:9: warning: while (true) loop has no exit [infinite-loop]",
+                "15:9: warning: unreachable statement [unreachable-code]",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_with_config_and_allow_comment() -> Result<()> {
+        let jack_code = "class Main {
+    function void main(int a) {
+        var int b; // jack-lint: allow(unused-variable)
+        // jack-lint: allow(unused-parameter, empty-if)
+        if (true) { }
+    }
+}";
+        assert_eq!(
+            lint(&Linter::new(), jack_code)?,
+            vec![
+                "2:19: missing return at the end of subroutine: \"main\" [missing-return]",
+                "2:28: warning: unused parameter: \"a\" [unused-parameter]",
+            ]
+        );
+
+        let config = LintConfig::parse(
+            "# project lints\nmissing-return = warn\n\nunused-parameter=allow\n",
+        )?;
+        let linter = Linter::new().with_config(config)?;
+        assert_eq!(
+            lint(&linter, jack_code)?,
+            vec![
+                "2:19: warning: missing return at the end of subroutine: \"main\" [missing-return]"
+            ]
+        );
+
+        let err = Linter::new()
+            .with_config(LintConfig::parse("unused = warn")?)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "unknown lint rule: \"unused\"");
+        let err = LintConfig::parse("empty-if = deny").unwrap_err();
+        assert_eq!(err.to_string(), "1: unknown lint level: \"deny\"");
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_write_only_variables() -> Result<()> {
+        let jack_code = "class Main {
+    field int x;
+    field Array cells;
+    method void run() {
+        var int count;
+        var Array buffer;
+        let x = 1;
+        let count = 0;
+        let count = count;
+        let buffer = Array.new(2);
+        let buffer[0] = 1;
+        let cells[0] = 2;
+        return;
+    }
+}";
+
+        assert_eq!(
+            lint(&Linter::new(), jack_code)?,
+            vec!["2:15: warning: unused field: \"x\" [unused-class-var]"]
+        );
+
+        let jack_code = "class Main {
+    function void main() {
+        var int x;
+        let x = 1;
+        return;
+    }
+}";
+        assert_eq!(
+            lint(&Linter::new(), jack_code)?,
+            vec!["3:17: warning: unused variable: \"x\" [unused-variable]"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use jack_tokenizer::JackTokenizer;
+use std::time::{Duration, Instant};
+
+// 1行あたりのトークン数がほぼ一定のJackコードを生成する
+fn generate_jack_code(lines: usize) -> String {
+    let mut code = String::from("class Main {\n  function void main() {\n    var int i;\n");
+    for n in 0..lines {
+        match n % 4 {
+            0 => code.push_str("    let i = i + 1; // comment\n"),
+            1 => code.push_str("    do Output.printString(\"jack\");\n"),
+            2 => code.push_str("    /* block */ if (i < 32767) { let i = -i; }\n"),
+            _ => code.push_str("    while (~(i = 0)) { let i = i / 2; }\n"),
+        }
+    }
+    code.push_str("    return;\n  }\n}\n");
+    code
+}
+
+fn tokenize_all(code: &str) -> usize {
+    let mut tokenizer = JackTokenizer::new(code.as_bytes()).unwrap();
+    let mut count = 0;
+    while tokenizer.has_more_tokens().unwrap() {
+        tokenizer.advance().unwrap();
+        tokenizer.token_type().unwrap();
+        count += 1;
+    }
+    count
+}
+
+// 複数回計測した最短時間を1行あたりの時間にする
+fn time_per_line(lines: usize) -> Duration {
+    let code = generate_jack_code(lines);
+    let elapsed = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            tokenize_all(&code);
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+    elapsed / lines as u32
+}
+
+// 1万行と10万行の1行あたりの時間の比を表示する。
+// 計測時間は環境に左右されるので失敗にはせず、比が1に近いかを目で確かめる
+fn report_linear_scaling(_: &mut Criterion) {
+    let small = time_per_line(10_000);
+    let large = time_per_line(100_000);
+    let ratio = large.as_secs_f64() / small.as_secs_f64();
+    println!("tokenize per line: 10000 lines {small:?}, 100000 lines {large:?}, ratio {ratio:.2}");
+}
+
+// 行数あたりのスループット(elem/s)が行数に依らず一定であれば線形時間。
+// 二乗時間であれば行数が10倍になるとスループットは1/10になる
+fn bench_tokenizer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize");
+    group.sample_size(10);
+    for lines in [10_000, 50_000, 100_000] {
+        let code = generate_jack_code(lines);
+        group.throughput(Throughput::Elements(lines as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &code, |b, code| {
+            b.iter(|| tokenize_all(code))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, report_linear_scaling, bench_tokenizer);
+criterion_main!(benches);
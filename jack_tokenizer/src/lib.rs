@@ -1,12 +1,13 @@
 use anyhow::{anyhow, Result};
-use regex::Regex;
 use std::{
-    io::{BufReader, Read},
-    str::FromStr,
+    collections::VecDeque,
+    fmt,
+    io::{BufRead, BufReader, Read},
 };
-use strum::IntoEnumIterator;
 use strum_macros::{AsRefStr, EnumIter, EnumString};
 
+const MAX_INT_CONST: u16 = 32767;
+
 const SYMBOLS: [char; 19] = [
     '{', '}', '(', ')', '[', ']', '.', ',', ';', '+', '-', '*', '/', '&', '|', '<', '>', '=', '~',
 ];
@@ -23,7 +24,7 @@ macro_rules! alphabet_letter {
     };
 }
 
-#[derive(Debug, PartialEq, AsRefStr, EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumIter)]
 pub enum TokenType {
     KeyWord,
     Symbol,
@@ -34,7 +35,7 @@ pub enum TokenType {
     StringConst,
 }
 
-#[derive(Debug, PartialEq, AsRefStr, EnumIter, EnumString)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumIter, EnumString)]
 #[strum(ascii_case_insensitive)]
 pub enum KeyWord {
     Class,
@@ -60,9 +61,211 @@ pub enum KeyWord {
     This,
 }
 
+impl KeyWord {
+    // 字句解析の識別子ごとに呼ばれるので、文字列を確保せずに照合する。大文字小文字は区別する
+    fn from_text(text: &str) -> Option<KeyWord> {
+        let keyword = match text {
+            "class" => KeyWord::Class,
+            "method" => KeyWord::Method,
+            "function" => KeyWord::Function,
+            "constructor" => KeyWord::Constructor,
+            "int" => KeyWord::Int,
+            "boolean" => KeyWord::Boolean,
+            "char" => KeyWord::Char,
+            "void" => KeyWord::Void,
+            "var" => KeyWord::Var,
+            "static" => KeyWord::Static,
+            "field" => KeyWord::Field,
+            "let" => KeyWord::Let,
+            "do" => KeyWord::Do,
+            "if" => KeyWord::If,
+            "else" => KeyWord::Else,
+            "while" => KeyWord::While,
+            "return" => KeyWord::Return,
+            "true" => KeyWord::True,
+            "false" => KeyWord::False,
+            "null" => KeyWord::Null,
+            "this" => KeyWord::This,
+            _ => return None,
+        };
+        Some(keyword)
+    }
+}
+
+// start,endはソース先頭からのバイトオフセット、line,columnは1始まり
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    // selfの先頭からotherの末尾までを覆うSpanを返す
+    pub fn join(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+// 種類ごとの値を持つトークン。パターンマッチで種類と値を同時に取り出せる
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Keyword(KeyWord),
+    Symbol(char),
+    Identifier(String),
+    IntConst(u16),
+    StringConst(String),
+}
+
+impl Token {
+    pub fn kind(&self) -> TokenType {
+        match self {
+            Token::Keyword(_) => TokenType::KeyWord,
+            Token::Symbol(_) => TokenType::Symbol,
+            Token::Identifier(_) => TokenType::Identifier,
+            Token::IntConst(_) => TokenType::IntConst,
+            Token::StringConst(_) => TokenType::StringConst,
+        }
+    }
+}
+
+// ソース上の位置と元の文字列を伴うトークン。textは文字列定数の場合も'"'を含む
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexeme {
+    pub token: Token,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Lexeme {
+    pub fn kind(&self) -> TokenType {
+        self.token.kind()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr)]
+pub enum TriviaKind {
+    // 改行は1つずつ独立したWhitespaceになる
+    Whitespace,
+    LineComment,
+    BlockComment,
+    // "/**"で始まるブロックコメント
+    DocComment,
+}
+
+// 字句解析で読み飛ばす空白やコメント
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Trivia {
+    fn is_newline(&self) -> bool {
+        self.kind == TriviaKind::Whitespace && self.text.ends_with('\n')
+    }
+}
+
+// トークンの前にある空白やコメント(leading)と、同じ行の改行までにある空白やコメント(trailing)を保持する
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenWithTrivia {
+    pub leading_trivia: Vec<Trivia>,
+    pub lexeme: Lexeme,
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+// すべてのトークンと最後のトークン以降のtriviaを連結すると元のソースと一致する
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TriviaTokens {
+    pub tokens: Vec<TokenWithTrivia>,
+    pub end_of_file_trivia: Vec<Trivia>,
+}
+
+impl fmt::Display for TriviaTokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            for trivia in &token.leading_trivia {
+                f.write_str(&trivia.text)?;
+            }
+            f.write_str(&token.lexeme.text)?;
+            for trivia in &token.trailing_trivia {
+                f.write_str(&trivia.text)?;
+            }
+        }
+        for trivia in &self.end_of_file_trivia {
+            f.write_str(&trivia.text)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    UnterminatedString { span: Span },
+    IntegerOutOfRange { value: String, span: Span },
+    IllegalCharacter { character: char, span: Span },
+    UnterminatedBlockComment { span: Span },
+    NewlineInString { span: Span },
+    Io { message: String, span: Span },
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnterminatedString { span }
+            | LexError::IntegerOutOfRange { span, .. }
+            | LexError::IllegalCharacter { span, .. }
+            | LexError::UnterminatedBlockComment { span }
+            | LexError::NewlineInString { span }
+            | LexError::Io { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnterminatedString { span } => {
+                write!(f, "{}: unterminated string constant", span)
+            }
+            LexError::IntegerOutOfRange { value, span } => write!(
+                f,
+                "{}: integer constant {} is out of range (0..={})",
+                span, value, MAX_INT_CONST
+            ),
+            LexError::IllegalCharacter { character, span } => {
+                write!(f, "{}: illegal character: {:?}", span, character)
+            }
+            LexError::UnterminatedBlockComment { span } => {
+                write!(f, "{}: unterminated block comment", span)
+            }
+            LexError::NewlineInString { span } => {
+                write!(f, "{}: newline in string constant", span)
+            }
+            LexError::Io { message, span } => write!(f, "{}: io error: {}", span, message),
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
+
+// トークン列は構築時に一度だけ字句解析し、以降はインデックスを進めるだけにする
 pub struct JackTokenizer {
-    tokens: Vec<String>,
-    current_token: Option<String>,
+    tokens: Vec<Lexeme>,
+    // 次にadvanceで読み進めるトークンの位置
+    next: usize,
+    current: Option<usize>,
 }
 
 impl JackTokenizer {
@@ -73,195 +276,490 @@ impl JackTokenizer {
 
         Ok(Self {
             tokens: parse_tokens(&buf)?,
-            current_token: None,
+            next: 0,
+            current: None,
         })
     }
 
     pub fn has_more_tokens(&mut self) -> Result<bool> {
-        Ok(self.tokens.iter().next().is_some())
+        Ok(self.next < self.tokens.len())
     }
 
     pub fn advance(&mut self) -> Result<()> {
         if self.has_more_tokens()? {
-            self.current_token = self.tokens.iter().next().cloned();
-            let mut tokens = self.tokens.clone().into_iter();
-            tokens.next();
-            self.tokens = tokens.collect();
+            self.current = Some(self.next);
+            self.next += 1;
         }
         Ok(())
     }
 
+    // 読み進めずにn個先のトークンを返す。peek(0)は次にadvanceで読み進めるトークン
+    pub fn peek(&self, n: usize) -> Option<&Lexeme> {
+        self.tokens.get(self.next + n)
+    }
+
+    pub fn current_token(&self) -> Option<&Lexeme> {
+        self.current.map(|index| &self.tokens[index])
+    }
+
+    pub fn current_span(&self) -> Option<Span> {
+        self.current_token().map(|token| token.span)
+    }
+
     pub fn token_type(&self) -> Result<TokenType> {
-        match &self.current_token {
-            Some(t) if KeyWord::iter().any(|k| k.as_ref().to_lowercase() == *t) => {
-                Ok(TokenType::KeyWord)
-            }
-            Some(t) if SYMBOLS.iter().any(|s| *s == t.chars().next().unwrap()) => {
-                Ok(TokenType::Symbol)
-            }
-            Some(t) if matches!(t.chars().next().unwrap(), _c @(number_letter!())) => {
-                Ok(TokenType::IntConst)
-            }
-            Some(t) if t.chars().next().unwrap() == '"' => Ok(TokenType::StringConst),
-            Some(t) if matches!(t.chars().next().unwrap(), _c @('_' | alphabet_letter!())) => {
-                Ok(TokenType::Identifier)
-            }
-            None => panic!("curret token is empty"),
-            t => panic!("un supported token type: {:?}", t),
-        }
+        Ok(self.lexeme()?.kind())
     }
 
     pub fn keyword(&self) -> Result<KeyWord> {
-        match &self.current_token {
-            Some(token) => match KeyWord::from_str(&token) {
-                Ok(keyword) => Ok(keyword),
-                Err(e) => panic!(
-                    "KeywordEnum parse error: {:?} token: {:?}",
-                    e, self.current_token
-                ),
-            },
-            None => panic!("current token is empty"),
+        match self.lexeme()?.token {
+            Token::Keyword(keyword) => Ok(keyword),
+            _ => Err(self.type_error(TokenType::KeyWord)),
         }
     }
 
     pub fn symbol(&self) -> Result<String> {
-        Ok(self.current_token.clone().unwrap())
+        match self.lexeme()?.token {
+            Token::Symbol(symbol) => Ok(symbol.to_string()),
+            _ => Err(self.type_error(TokenType::Symbol)),
+        }
     }
 
     pub fn identifer(&self) -> Result<String> {
-        Ok(self.current_token.clone().unwrap())
+        match &self.lexeme()?.token {
+            Token::Identifier(identifier) => Ok(identifier.clone()),
+            _ => Err(self.type_error(TokenType::Identifier)),
+        }
     }
 
     pub fn int_val(&self) -> Result<u16> {
-        Ok(self
-            .current_token
-            .clone()
-            .unwrap()
-            .parse::<u16>()
-            .expect("int_val parse failed"))
+        match self.lexeme()?.token {
+            Token::IntConst(value) => Ok(value),
+            _ => Err(self.type_error(TokenType::IntConst)),
+        }
     }
 
     pub fn string_val(&self) -> Result<String> {
-        Ok(self
-            .current_token
-            .clone()
-            .unwrap()
-            .as_str()
-            .chars()
-            .filter(|c| *c != '"')
-            .collect())
+        match &self.lexeme()?.token {
+            Token::StringConst(value) => Ok(value.clone()),
+            _ => Err(self.type_error(TokenType::StringConst)),
+        }
+    }
+
+    fn lexeme(&self) -> Result<&Lexeme> {
+        self.current_token()
+            .ok_or_else(|| anyhow!("current token is empty"))
+    }
+
+    fn type_error(&self, kind: TokenType) -> anyhow::Error {
+        match self.current_token() {
+            Some(lexeme) => anyhow!(
+                "{}: current token is not {}: {:?}",
+                lexeme.span,
+                kind.as_ref(),
+                lexeme.text
+            ),
+            None => anyhow!("current token is empty"),
+        }
     }
 }
 
-fn parse_tokens(input: &str) -> Result<Vec<String>> {
-    let mut tokens: Vec<String> = Vec::new();
+// 1回のnextでadvanceと同様に1つ読み進め、読み進めたトークンを返す。
+// 字句解析のエラーはnewで報告済みなので、エラーを返すことはない
+impl Iterator for JackTokenizer {
+    type Item = Token;
 
-    let ignore_comment_regex = Regex::new(r"//.*(\n|$)|\n?/\*[\s\S]*?\*/\n?")?;
-    let comment_ignored_input = ignore_comment_regex.replace_all(input, "").to_string();
-    let mut input = comment_ignored_input.as_str();
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokens.get(self.next)?.token.clone();
+        self.current = Some(self.next);
+        self.next += 1;
+        Some(token)
+    }
+}
 
-    while input.chars().next().is_some() {
-        match input.chars().next() {
-            // whitespace
-            Some(c) if c.is_whitespace() => {
-                let mut chars = input.chars();
-                chars.next();
-                input = chars.as_str();
-                Ok(())
+// 入力を行ごとに分けて字句解析する場合に、行をまたいで引き継ぐ状態
+#[derive(Debug, Clone, Copy)]
+struct LexState {
+    // 入力の先頭からのバイトオフセット
+    offset: usize,
+    line: usize,
+    column: usize,
+    // 閉じられていないブロックコメントの開始位置
+    block_comment: Option<Span>,
+}
+
+impl LexState {
+    fn new() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+            block_comment: None,
+        }
+    }
+
+    fn position(&self) -> Span {
+        Span {
+            start: self.offset,
+            end: self.offset,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    // 入力の終端でブロックコメントが閉じられていなければエラーにする
+    fn finish(&self) -> Result<(), LexError> {
+        match self.block_comment {
+            Some(start) => Err(LexError::UnterminatedBlockComment {
+                span: Span {
+                    end: self.offset,
+                    ..start
+                },
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+    state: LexState,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str, state: LexState) -> Self {
+        Self {
+            input,
+            pos: 0,
+            state,
+        }
+    }
+
+    // 入力をすべて読み進めた後の状態を返す
+    fn into_state(self) -> LexState {
+        self.state
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        self.state.offset += c.len_utf8();
+        if c == '\n' {
+            self.state.line += 1;
+            self.state.column = 1;
+        } else {
+            self.state.column += 1;
+        }
+        Some(c)
+    }
+
+    fn bump_while(&mut self, f: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        Span {
+            end: self.state.offset,
+            ..start
+        }
+    }
+
+    fn current_position(&self) -> Span {
+        self.state.position()
+    }
+
+    // 現在位置から空白またはコメントを1つ読み進めて返す。トークンの先頭であればNoneを返す
+    fn next_trivia(&mut self) -> Result<Option<Trivia>, LexError> {
+        let start = self.current_position();
+        let begin = self.pos;
+        let rest = self.rest();
+        let kind = if rest.starts_with("\r\n") || rest.starts_with('\n') {
+            self.bump_while(|c| c == '\r');
+            self.bump();
+            TriviaKind::Whitespace
+        } else if rest.starts_with(|c: char| c.is_whitespace() && c != '\n' && c != '\r') {
+            self.bump_while(|c| c.is_whitespace() && c != '\n' && c != '\r');
+            TriviaKind::Whitespace
+        } else if rest.starts_with('\r') {
+            self.bump();
+            TriviaKind::Whitespace
+        } else if rest.starts_with("//") {
+            self.bump_while(|c| c != '\n' && c != '\r');
+            TriviaKind::LineComment
+        } else if rest.starts_with("/*") {
+            let kind = if rest.starts_with("/**") && !rest.starts_with("/**/") {
+                TriviaKind::DocComment
+            } else {
+                TriviaKind::BlockComment
+            };
+            self.bump();
+            self.bump();
+            while !self.rest().starts_with("*/") {
+                if self.bump().is_none() {
+                    return Err(LexError::UnterminatedBlockComment {
+                        span: self.span_from(start),
+                    });
+                }
+            }
+            self.bump();
+            self.bump();
+            kind
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(Trivia {
+            kind,
+            text: self.input[begin..self.pos].to_string(),
+            span: self.span_from(start),
+        }))
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), LexError> {
+        loop {
+            if self.state.block_comment.is_some() {
+                // ブロックコメントが閉じられないまま入力の末尾に達した場合は次の入力に引き継ぐ
+                while !self.rest().starts_with("*/") {
+                    if self.bump().is_none() {
+                        return Ok(());
+                    }
+                }
+                self.bump();
+                self.bump();
+                self.state.block_comment = None;
+                continue;
+            }
+            let rest = self.rest();
+            if rest.starts_with(char::is_whitespace) {
+                self.bump_while(char::is_whitespace);
+            } else if rest.starts_with("//") {
+                self.bump_while(|c| c != '\n');
+            } else if rest.starts_with("/*") {
+                self.state.block_comment = Some(self.current_position());
+                self.bump();
+                self.bump();
+            } else {
+                return Ok(());
             }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Lexeme>, LexError> {
+        self.skip_whitespace_and_comments()?;
+        let start = self.current_position();
+        let begin = self.pos;
+        let token = match self.peek() {
+            None => return Ok(None),
             // keyword,identifer
-            Some(_c @ ('_' | alphabet_letter!())) => {
-                let mut chars = input.chars();
-                let mut token = chars.next().unwrap().to_string();
-                input = chars.as_str();
-                while matches!(
-                    input.chars().next(),
-                    Some(_c @ ('_' | alphabet_letter!() | number_letter!()))
-                ) {
-                    let mut chars = input.chars();
-                    token += &chars.next().unwrap().to_string();
-                    input = chars.as_str();
+            Some('_' | alphabet_letter!()) => {
+                self.bump_while(|c| matches!(c, '_' | alphabet_letter!() | number_letter!()));
+                let text = &self.input[begin..self.pos];
+                match KeyWord::from_text(text) {
+                    Some(keyword) => Token::Keyword(keyword),
+                    None => Token::Identifier(text.to_string()),
                 }
-                tokens.push(token);
-                Ok(())
             }
             // symbol
-            Some(c) if SYMBOLS.iter().any(|symbol| c == *symbol) => {
-                let mut chars = input.chars();
-                let token = chars.next().unwrap().to_string();
-                input = chars.as_str();
-                tokens.push(token);
-                Ok(())
+            Some(c) if SYMBOLS.contains(&c) => {
+                self.bump();
+                Token::Symbol(c)
             }
             // integer
-            Some(_c @ (number_letter!())) => {
-                let mut chars = input.chars();
-                let mut token = chars.next().unwrap().to_string();
-                input = chars.as_str();
-                while matches!(input.chars().next(), Some(_c @ (number_letter!()))) {
-                    let mut chars = input.chars();
-                    token += &chars.next().unwrap().to_string();
-                    input = chars.as_str();
+            Some(number_letter!()) => {
+                self.bump_while(|c| c.is_ascii_digit());
+                let value = &self.input[begin..self.pos];
+                match value.parse::<u16>() {
+                    Ok(v) if v <= MAX_INT_CONST => Token::IntConst(v),
+                    _ => {
+                        return Err(LexError::IntegerOutOfRange {
+                            value: value.to_string(),
+                            span: self.span_from(start),
+                        })
+                    }
                 }
-                tokens.push(token);
-                Ok(())
             }
             //stringConst
-            Some(c) if c == '"' => {
+            Some('"') => {
                 // '"'を見つけたら次に'"'を見つけるまでの文字をtokenとする
-                let mut chars = input.chars();
-                let mut token = chars.next().unwrap().to_string();
-                input = chars.as_str();
-                while input.chars().next() != Some('"') {
-                    chars = input.chars();
-                    token += &chars.next().unwrap().to_string();
-                    input = chars.as_str();
-                }
-                chars = input.chars();
-                token += &chars.next().unwrap().to_string();
-                input = chars.as_str();
-                tokens.push(token);
-                Ok(())
+                self.bump();
+                self.bump_while(|c| !matches!(c, '"' | '\n'));
+                match self.peek() {
+                    Some('"') => self.bump(),
+                    Some(_) => {
+                        return Err(LexError::NewlineInString {
+                            span: self.span_from(start),
+                        })
+                    }
+                    None => {
+                        return Err(LexError::UnterminatedString {
+                            span: self.span_from(start),
+                        })
+                    }
+                };
+                Token::StringConst(self.input[begin + 1..self.pos - 1].to_string())
             }
-            None => Ok(()),
-            c => Err(anyhow!("un supported token: {:?} input: {:?}", c, input)),
-        }?
+            Some(character) => {
+                self.bump();
+                return Err(LexError::IllegalCharacter {
+                    character,
+                    span: self.span_from(start),
+                });
+            }
+        };
+        let span = self.span_from(start);
+        Ok(Some(Lexeme {
+            token,
+            text: self.input[begin..self.pos].to_string(),
+            span,
+        }))
+    }
+}
+
+fn parse_tokens(input: &str) -> Result<Vec<Lexeme>, LexError> {
+    let mut tokens: Vec<Lexeme> = Vec::new();
+    let mut lexer = Lexer::new(input, LexState::new());
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
     }
+    lexer.into_state().finish()?;
 
     Ok(tokens)
 }
 
+// 空白やコメントを捨てずに各トークンに付与して字句解析する
+pub fn tokenize_with_trivia(input: &str) -> Result<TriviaTokens, LexError> {
+    let mut lexer = Lexer::new(input, LexState::new());
+    let mut tokens = Vec::new();
+    let mut leading_trivia = Vec::new();
+    loop {
+        while let Some(trivia) = lexer.next_trivia()? {
+            leading_trivia.push(trivia);
+        }
+        let Some(lexeme) = lexer.next_token()? else {
+            break;
+        };
+        let mut trailing_trivia = Vec::new();
+        while let Some(trivia) = lexer.next_trivia()? {
+            let is_newline = trivia.is_newline();
+            trailing_trivia.push(trivia);
+            if is_newline {
+                break;
+            }
+        }
+        tokens.push(TokenWithTrivia {
+            leading_trivia: std::mem::take(&mut leading_trivia),
+            lexeme,
+            trailing_trivia,
+        });
+    }
+
+    Ok(TriviaTokens {
+        tokens,
+        end_of_file_trivia: leading_trivia,
+    })
+}
+
+// BufReadから1行ずつ読み込んで字句解析する。保持するのは読み込み中の1行分のトークンのみ
+pub struct TokenStream<R> {
+    reader: R,
+    line: String,
+    state: LexState,
+    tokens: VecDeque<Lexeme>,
+    // 同じ行のエラーより前のトークンを返し終えてから返す
+    error: Option<LexError>,
+    finished: bool,
+}
+
+impl<R: BufRead> TokenStream<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            state: LexState::new(),
+            tokens: VecDeque::new(),
+            error: None,
+            finished: false,
+        }
+    }
+
+    fn read_line(&mut self) -> Result<(), LexError> {
+        self.line.clear();
+        let size = self
+            .reader
+            .read_line(&mut self.line)
+            .map_err(|e| LexError::Io {
+                message: e.to_string(),
+                span: self.state.position(),
+            })?;
+        if size == 0 {
+            self.finished = true;
+            return self.state.finish();
+        }
+        let mut lexer = Lexer::new(&self.line, self.state);
+        while let Some(token) = lexer.next_token()? {
+            self.tokens.push_back(token);
+        }
+        self.state = lexer.into_state();
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for TokenStream<R> {
+    type Item = Result<Lexeme, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.tokens.pop_front() {
+                return Some(Ok(token));
+            }
+            if let Some(e) = self.error.take() {
+                return Some(Err(e));
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(e) = self.read_line() {
+                self.finished = true;
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use regex::Regex;
 
-    #[test]
-    fn regex_playground() -> Result<()> {
-        let re = Regex::new(r"//.*(\n|$)")?;
-        let result = re.replace("// this comment", "");
-        assert_eq!(result, "");
+    fn token_texts(input: &str) -> Result<Vec<String>> {
+        Ok(parse_tokens(input)?.into_iter().map(|t| t.text).collect())
+    }
 
-        let re = Regex::new(r"/\*[\s\S]*?\*/")?;
-        let result = re.replace(
+    #[test]
+    fn test_parse_token_ignore_comment() -> Result<()> {
+        assert!(token_texts("// this comment")?.is_empty());
+        assert!(token_texts(
             "/**
          * comment 
-         */",
-            "",
-        );
-        assert_eq!(result, "");
-
-        let re = Regex::new(r"/\*[\s\S]*?\*/")?;
-        let result = re.replace(
+         */"
+        )?
+        .is_empty());
+        assert!(token_texts(
             "/*
          * comment 
-         */",
-            "",
-        );
-        assert_eq!(result, "");
+         */"
+        )?
+        .is_empty());
 
-        let re = Regex::new(r"//.*(\n|$)|\n?/\*[\s\S]*?\*/\n?")?;
-        let result = re.replace_all(
+        let actual = token_texts(
             "/*
 * comment
 */
@@ -270,16 +768,108 @@ let a = \"hello\";
 /**
 * comment
 */",
-            "",
+        )?;
+        assert_eq!(actual, vec!["let", "a", "=", "\"hello\"", ";"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_token_when_unterminated_block_comment() {
+        assert_eq!(
+            parse_tokens("let a = 1; /* comment"),
+            Err(LexError::UnterminatedBlockComment {
+                span: Span {
+                    start: 11,
+                    end: 21,
+                    line: 1,
+                    column: 12
+                }
+            })
         );
-        assert_eq!(result, "let a = \"hello\";\n");
+    }
+
+    #[test]
+    fn test_parse_token_when_lex_error() {
+        assert_eq!(
+            parse_tokens("let s = \"abc"),
+            Err(LexError::UnterminatedString {
+                span: Span {
+                    start: 8,
+                    end: 12,
+                    line: 1,
+                    column: 9
+                }
+            })
+        );
+        assert_eq!(
+            parse_tokens("let s = \"abc\n\";"),
+            Err(LexError::NewlineInString {
+                span: Span {
+                    start: 8,
+                    end: 12,
+                    line: 1,
+                    column: 9
+                }
+            })
+        );
+        assert_eq!(
+            parse_tokens("let i = 32768;"),
+            Err(LexError::IntegerOutOfRange {
+                value: "32768".to_string(),
+                span: Span {
+                    start: 8,
+                    end: 13,
+                    line: 1,
+                    column: 9
+                }
+            })
+        );
+        assert!(parse_tokens("let i = 32767;").is_ok());
+        assert_eq!(
+            parse_tokens("let a\n  = #;"),
+            Err(LexError::IllegalCharacter {
+                character: '#',
+                span: Span {
+                    start: 10,
+                    end: 11,
+                    line: 2,
+                    column: 5
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_jack_tokenizer_when_lex_error() {
+        let file_content = std::io::Cursor::new("let s = \"abc".as_bytes());
+        let err = JackTokenizer::new(file_content).err().unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<LexError>(),
+            Some(LexError::UnterminatedString { .. })
+        ));
+        assert_eq!(err.to_string(), "1:9: unterminated string constant");
+    }
+
+    #[test]
+    fn test_jack_tokenizer_accessor_error() -> Result<()> {
+        let file_content = std::io::Cursor::new("foo".as_bytes());
+        let mut tokenizer = JackTokenizer::new(file_content)?;
+        assert!(tokenizer.token_type().is_err());
+
+        tokenizer.advance()?;
+        assert_eq!(tokenizer.token_type()?, TokenType::Identifier);
+        assert!(tokenizer.keyword().is_err());
+        assert!(tokenizer.int_val().is_err());
+        assert_eq!(tokenizer.identifer()?, "foo");
         Ok(())
     }
+
     #[test]
     fn test_parse_token_when_string_const() {
         let input = r#""negative" "positive""#;
-        let mut actual = vec!["\"positive\"", "\"negative\""];
-        assert_eq!(parse_tokens(input).unwrap().sort(), actual.sort());
+        let actual = vec!["\"negative\"", "\"positive\""];
+        assert_eq!(token_texts(input).unwrap(), actual);
     }
 
     #[test]
@@ -309,7 +899,22 @@ let a = \"hello\";
             ";",
             "}",
         ];
-        assert_eq!(parse_tokens(input).unwrap(), actual);
+        assert_eq!(token_texts(input).unwrap(), actual);
+
+        let tokens: Vec<Token> = parse_tokens("class Class classes this")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Keyword(KeyWord::Class),
+                Token::Identifier("Class".to_string()),
+                Token::Identifier("classes".to_string()),
+                Token::Keyword(KeyWord::This),
+            ]
+        );
     }
 
     #[test]
@@ -345,7 +950,7 @@ let a = \"hello\";
             ";",
             "}",
         ];
-        assert_eq!(parse_tokens(input).unwrap(), actual);
+        assert_eq!(token_texts(input).unwrap(), actual);
     }
 
     #[test]
@@ -360,20 +965,247 @@ let a = \"hello\";
         );
         let mut tokenizer = JackTokenizer::new(file_content)?;
 
-        assert_eq!(tokenizer.current_token.clone(), None);
+        assert_eq!(tokenizer.current_token().cloned(), None);
 
         tokenizer.advance()?;
-        assert_eq!(tokenizer.current_token.clone().unwrap(), "if".to_string());
+        assert_eq!(
+            tokenizer.current_token().cloned().unwrap().text,
+            "if".to_string()
+        );
         assert_eq!(tokenizer.token_type()?, TokenType::KeyWord);
 
         tokenizer.advance()?;
-        assert_eq!(tokenizer.current_token.clone().unwrap(), "(".to_string());
+        assert_eq!(
+            tokenizer.current_token().cloned().unwrap().text,
+            "(".to_string()
+        );
         assert_eq!(tokenizer.token_type()?, TokenType::Symbol);
 
         tokenizer.advance()?;
-        assert_eq!(tokenizer.current_token.clone().unwrap(), "x".to_string());
+        assert_eq!(
+            tokenizer.current_token().cloned().unwrap().text,
+            "x".to_string()
+        );
         assert_eq!(tokenizer.token_type()?, TokenType::Identifier);
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_token_span() -> Result<()> {
+        let input = "class Main {\n    // comment\n    field int x;\n}";
+        let tokens = parse_tokens(input)?;
+
+        assert_eq!(
+            tokens[0].span,
+            Span {
+                start: 0,
+                end: 5,
+                line: 1,
+                column: 1
+            }
+        );
+        let field = &tokens[3];
+        assert_eq!(field.kind(), TokenType::KeyWord);
+        assert_eq!(&input[field.span.start..field.span.end], "field");
+        assert_eq!((field.span.line, field.span.column), (3, 5));
+        let x = &tokens[5];
+        assert_eq!(x.kind(), TokenType::Identifier);
+        assert_eq!((x.span.line, x.span.column), (3, 15));
+        assert_eq!(x.span.to_string(), "3:15");
+        Ok(())
+    }
+
+    #[test]
+    fn test_jack_tokenizer_current_span() -> Result<()> {
+        let file_content = std::io::Cursor::new("let a =\n  \"abc\";".as_bytes());
+        let mut tokenizer = JackTokenizer::new(file_content)?;
+        assert_eq!(tokenizer.current_span(), None);
+
+        (0..4).try_for_each(|_| tokenizer.advance())?;
+        assert_eq!(tokenizer.token_type()?, TokenType::StringConst);
+        assert_eq!(
+            tokenizer.current_span(),
+            Some(Span {
+                start: 10,
+                end: 15,
+                line: 2,
+                column: 3
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_jack_tokenizer_peek() -> Result<()> {
+        let mut tokenizer = JackTokenizer::new("do f();".as_bytes())?;
+        assert_eq!(tokenizer.peek(0).map(|t| t.text.as_str()), Some("do"));
+
+        tokenizer.advance()?;
+        assert_eq!(tokenizer.peek(0).map(|t| t.text.as_str()), Some("f"));
+        assert_eq!(
+            tokenizer.peek(1).map(|t| (t.kind(), t.text.as_str())),
+            Some((TokenType::Symbol, "("))
+        );
+        assert_eq!(tokenizer.peek(4), None);
+        // peekしても現在のトークンは変わらない
+        assert_eq!(tokenizer.keyword()?, KeyWord::Do);
+        assert!(tokenizer.identifer().is_err());
+
+        (0..4).try_for_each(|_| tokenizer.advance())?;
+        assert_eq!(tokenizer.symbol()?, ";");
+        assert_eq!(tokenizer.peek(0), None);
+        assert!(!tokenizer.has_more_tokens()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_jack_tokenizer_iterator() -> Result<()> {
+        let tokenizer = JackTokenizer::new("let s = \"a b\"; do f(12);".as_bytes())?;
+        let tokens = tokenizer.collect::<Vec<_>>();
+
+        assert_eq!(
+            tokens[..5],
+            [
+                Token::Keyword(KeyWord::Let),
+                Token::Identifier("s".to_string()),
+                Token::Symbol('='),
+                Token::StringConst("a b".to_string()),
+                Token::Symbol(';'),
+            ]
+        );
+        assert_eq!(tokens[8], Token::IntConst(12));
+        assert_eq!(tokens.len(), 11);
+
+        // nextで読み進めた位置はadvanceと共有される
+        let mut tokenizer = JackTokenizer::new("do f();".as_bytes())?;
+        tokenizer.next();
+        assert_eq!(tokenizer.keyword()?, KeyWord::Do);
+        tokenizer.advance()?;
+        assert_eq!(tokenizer.next(), Some(Token::Symbol('(')));
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_stream_is_identical_to_parse_tokens() -> Result<()> {
+        let square = std::fs::read_to_string("../test_files/Square/Square.jack")?;
+        let inputs = [
+            square.as_str(),
+            "class A { /* block\n comment ** / \n */ field int x; } // end",
+            "let s = \"日本語\";\r\n/**/let x=1;/*\n\n*/",
+            "let x = 1;\nlet y = 2 # 3;",
+            "let x = 1;\n/* unterminated\n comment",
+            "let s = \"abc\nd\";",
+            "",
+        ];
+        for input in inputs {
+            // 行の途中でバッファが区切られても結果は変わらない
+            let stream = TokenStream::new(BufReader::with_capacity(4, input.as_bytes()))
+                .collect::<Result<Vec<_>, _>>();
+            assert_eq!(stream, parse_tokens(input), "input: {:?}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_stream_yields_tokens_before_error() {
+        let mut stream = TokenStream::new("let\nx # y".as_bytes());
+        let texts = stream
+            .by_ref()
+            .take(2)
+            .map(|t| t.map(|t| t.text))
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(texts, Ok(vec!["let".to_string(), "x".to_string()]));
+        assert!(matches!(
+            stream.next(),
+            Some(Err(LexError::IllegalCharacter { character: '#', .. }))
+        ));
+        assert_eq!(stream.next(), None);
+    }
+
+    #[test]
+    fn test_tokenize_with_trivia_is_lossless() -> Result<()> {
+        let square = std::fs::read_to_string("../test_files/Square/Square.jack")?;
+        let inputs = [
+            square.as_str(),
+            "",
+            "  // only comment\r\n",
+            "class A {/**/}\r\n\r\n/* a\n b */ \t// c\n",
+            "let s=\"x\";/** doc */ let t = 1; \r \n",
+        ];
+        for input in inputs {
+            let trivia_tokens = tokenize_with_trivia(input)?;
+            assert_eq!(trivia_tokens.to_string(), input);
+            // triviaを除いたトークンは通常の字句解析と一致する
+            let lexemes = trivia_tokens
+                .tokens
+                .into_iter()
+                .map(|t| t.lexeme)
+                .collect::<Vec<_>>();
+            assert_eq!(lexemes, parse_tokens(input)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokenize_with_trivia_attaches_trivia() -> Result<()> {
+        let input = "/** Main */\nclass Main { // body\n  /* x */ field int x;\n}\n// end\n";
+        let trivia_tokens = tokenize_with_trivia(input)?;
+        let trivia = |trivia: &[Trivia]| {
+            trivia
+                .iter()
+                .map(|t| (t.kind, t.text.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        let class = &trivia_tokens.tokens[0];
+        assert_eq!(
+            trivia(&class.leading_trivia),
+            vec![
+                (TriviaKind::DocComment, "/** Main */".to_string()),
+                (TriviaKind::Whitespace, "\n".to_string()),
+            ]
+        );
+        assert_eq!(
+            trivia(&class.trailing_trivia),
+            vec![(TriviaKind::Whitespace, " ".to_string())]
+        );
+        let brace = &trivia_tokens.tokens[2];
+        assert_eq!(
+            trivia(&brace.trailing_trivia),
+            vec![
+                (TriviaKind::Whitespace, " ".to_string()),
+                (TriviaKind::LineComment, "// body".to_string()),
+                (TriviaKind::Whitespace, "\n".to_string()),
+            ]
+        );
+        let field = &trivia_tokens.tokens[3];
+        assert_eq!(
+            trivia(&field.leading_trivia),
+            vec![
+                (TriviaKind::Whitespace, "  ".to_string()),
+                (TriviaKind::BlockComment, "/* x */".to_string()),
+                (TriviaKind::Whitespace, " ".to_string()),
+            ]
+        );
+        assert_eq!(
+            trivia(&trivia_tokens.end_of_file_trivia),
+            vec![
+                (TriviaKind::LineComment, "// end".to_string()),
+                (TriviaKind::Whitespace, "\n".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokenize_with_trivia("x /* y").unwrap_err(),
+            LexError::UnterminatedBlockComment {
+                span: Span {
+                    start: 2,
+                    end: 6,
+                    line: 1,
+                    column: 3
+                }
+            }
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,990 @@
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use jack_tokenizer::Span;
+use symbol_table::SymbolTable;
+
+use jack_ast::{
+    Class, ClassVarDec, Expression, Identifier, LetStatement, Parameter, ReturnType, Statement,
+    SubroutineBody, SubroutineCall, SubroutineDec, Term, Type, VarDec,
+};
+
+// 拡張出力で<identifier>に付与する属性値
+const CLASS: &str = "class";
+const SUBROUTINE: &str = "subroutine";
+const DEFINED: &str = "defined";
+const USED: &str = "used";
+
+// 構文木の出力形式。ParseTreeWriterが構文木を走査しながら呼び出す
+// トークン列の位置が与えられない場合、キーワードや記号のspanはNoneになる。
+// parameterList等の位置を持たないノードのspanもNoneで、子要素の範囲から求める
+pub trait TreeFormat {
+    fn start_node(&mut self, out: &mut dyn Write, kind: &str, span: Option<Span>) -> Result<()>;
+
+    fn end_node(&mut self, out: &mut dyn Write, kind: &str) -> Result<()>;
+
+    fn token(
+        &mut self,
+        out: &mut dyn Write,
+        kind: &str,
+        text: &str,
+        span: Option<Span>,
+        attributes: &[(&str, String)],
+    ) -> Result<()>;
+}
+
+// nand2tetrisのxml(10章、11章)
+#[derive(Debug, Default)]
+pub struct XmlFormat;
+
+impl TreeFormat for XmlFormat {
+    fn start_node(&mut self, out: &mut dyn Write, kind: &str, _: Option<Span>) -> Result<()> {
+        writeln!(out, "<{kind}>")?;
+        Ok(())
+    }
+
+    fn end_node(&mut self, out: &mut dyn Write, kind: &str) -> Result<()> {
+        writeln!(out, "</{kind}>")?;
+        Ok(())
+    }
+
+    fn token(
+        &mut self,
+        out: &mut dyn Write,
+        kind: &str,
+        text: &str,
+        _: Option<Span>,
+        attributes: &[(&str, String)],
+    ) -> Result<()> {
+        let text = if kind == "symbol" {
+            escape_xml_symbol(text)
+        } else {
+            text
+        };
+        write!(out, "<{kind}")?;
+        for (name, value) in attributes {
+            write!(out, " {name}=\"{value}\"")?;
+        }
+        writeln!(out, "> {text} </{kind}>")?;
+        Ok(())
+    }
+}
+
+fn escape_xml_symbol(v: &str) -> &str {
+    match v {
+        "<" => "&lt;",
+        ">" => "&gt;",
+        "\"" => "&quot;",
+        "&" => "&amp;",
+        _ => v,
+    }
+}
+
+// {"kind", "span", "children"}のノードと{"kind", "text", "span"}のトークンからなるJSON
+// ルートのノードを閉じたときにまとめて書き込む
+#[derive(Debug, Default)]
+pub struct JsonFormat {
+    nodes: Vec<JsonNode>,
+}
+
+#[derive(Debug)]
+struct JsonNode {
+    kind: String,
+    span: Option<Span>,
+    children: Vec<Value>,
+    // 子要素が覆う範囲。spanが与えられなかったノードはこの範囲を使う
+    children_span: Option<Span>,
+}
+
+impl JsonFormat {
+    fn push_child(&mut self, out: &mut dyn Write, child: Value, span: Option<Span>) -> Result<()> {
+        match self.nodes.last_mut() {
+            Some(parent) => {
+                parent.children.push(child);
+                parent.children_span = match (parent.children_span, span) {
+                    (Some(children_span), Some(span)) => Some(children_span.join(span)),
+                    (children_span, span) => children_span.or(span),
+                };
+            }
+            None => {
+                serde_json::to_writer_pretty(&mut *out, &child)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TreeFormat for JsonFormat {
+    fn start_node(&mut self, _: &mut dyn Write, kind: &str, span: Option<Span>) -> Result<()> {
+        self.nodes.push(JsonNode {
+            kind: kind.to_string(),
+            span,
+            children: Vec::new(),
+            children_span: None,
+        });
+        Ok(())
+    }
+
+    fn end_node(&mut self, out: &mut dyn Write, _: &str) -> Result<()> {
+        let Some(node) = self.nodes.pop() else {
+            return Ok(());
+        };
+        let span = node.span.or(node.children_span);
+        let node = json!({
+            "kind": node.kind,
+            "span": json_span(span),
+            "children": node.children,
+        });
+        self.push_child(out, node, span)
+    }
+
+    fn token(
+        &mut self,
+        out: &mut dyn Write,
+        kind: &str,
+        text: &str,
+        span: Option<Span>,
+        attributes: &[(&str, String)],
+    ) -> Result<()> {
+        let mut token = Map::new();
+        token.insert("kind".to_string(), json!(kind));
+        token.insert("text".to_string(), json!(text));
+        token.insert("span".to_string(), json_span(span));
+        if !attributes.is_empty() {
+            let attributes: Map<String, Value> = attributes
+                .iter()
+                .map(|(name, value)| (name.to_string(), json!(value)))
+                .collect();
+            token.insert("attributes".to_string(), Value::Object(attributes));
+        }
+        self.push_child(out, Value::Object(token), span)
+    }
+}
+
+fn json_span(span: Option<Span>) -> Value {
+    match span {
+        Some(span) => json!({
+            "start": span.start,
+            "end": span.end,
+            "line": span.line,
+            "column": span.column,
+        }),
+        None => Value::Null,
+    }
+}
+
+// (kind child...)の形で1行に1つのノードかトークンを書き込むS式
+#[derive(Debug, Default)]
+pub struct SexpFormat {
+    depth: usize,
+}
+
+impl SexpFormat {
+    fn write_indent(&self, out: &mut dyn Write) -> Result<()> {
+        if self.depth > 0 {
+            write!(out, "\n{}", "  ".repeat(self.depth))?;
+        }
+        Ok(())
+    }
+}
+
+impl TreeFormat for SexpFormat {
+    fn start_node(&mut self, out: &mut dyn Write, kind: &str, _: Option<Span>) -> Result<()> {
+        self.write_indent(out)?;
+        write!(out, "({kind}")?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn end_node(&mut self, out: &mut dyn Write, _: &str) -> Result<()> {
+        self.depth = self.depth.saturating_sub(1);
+        write!(out, ")")?;
+        if self.depth == 0 {
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    fn token(
+        &mut self,
+        out: &mut dyn Write,
+        kind: &str,
+        text: &str,
+        _: Option<Span>,
+        attributes: &[(&str, String)],
+    ) -> Result<()> {
+        self.write_indent(out)?;
+        write!(out, "({kind} {text:?}")?;
+        for (name, value) in attributes {
+            write!(out, " :{name} {value:?}")?;
+        }
+        write!(out, ")")?;
+        Ok(())
+    }
+}
+
+pub struct ParseTreeWriter {
+    writer: Arc<Mutex<dyn Write>>,
+    format: Box<dyn TreeFormat>,
+    // Someの場合は<identifier>にcategory,index,usage属性を付与する
+    symbol_table: Option<SymbolTable>,
+    // ソースのトークン列の位置。構文木の葉はソースのトークンと同じ順序で現れる
+    token_spans: Vec<Span>,
+    next_token: usize,
+}
+
+impl ParseTreeWriter {
+    pub fn new(writer: Arc<Mutex<dyn Write>>) -> Self {
+        Self {
+            writer,
+            format: Box::new(XmlFormat),
+            symbol_table: None,
+            token_spans: Vec::new(),
+            next_token: 0,
+        }
+    }
+
+    pub fn with_format(mut self, format: impl TreeFormat + 'static) -> Self {
+        self.format = Box::new(format);
+        self
+    }
+
+    pub fn with_symbol_table(mut self, symbol_table: SymbolTable) -> Self {
+        self.symbol_table = Some(symbol_table);
+        self
+    }
+
+    // キーワードや記号にも位置を付与する場合は、構文木と同じソースのトークン列の位置を渡す
+    pub fn with_token_spans(mut self, token_spans: Vec<Span>) -> Self {
+        self.token_spans = token_spans;
+        self
+    }
+
+    pub fn write_tree(&mut self, class: &Class) -> Result<()> {
+        self.write_class(class)
+    }
+
+    fn write_class(&mut self, class: &Class) -> Result<()> {
+        let tag_name = "class";
+        self.start_node(tag_name, Some(class.span))?;
+        self.write_keyword("class")?;
+        self.write_name(&class.name, CLASS, DEFINED)?;
+        self.write_symbol("{")?;
+        class
+            .class_var_decs
+            .iter()
+            .try_for_each(|class_var_dec| self.write_class_var_dec(class_var_dec))?;
+        class
+            .subroutine_decs
+            .iter()
+            .try_for_each(|subroutine_dec| self.write_subroutine_dec(subroutine_dec))?;
+        self.write_symbol("}")?;
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_class_var_dec(&mut self, class_var_dec: &ClassVarDec) -> Result<()> {
+        let tag_name = "classVarDec";
+        self.start_node(tag_name, Some(class_var_dec.span))?;
+        self.write_keyword(class_var_dec.kind.as_ref())?;
+        self.write_type(&class_var_dec.var_type)?;
+        self.write_var_names(&class_var_dec.names)?;
+        self.write_symbol(";")?;
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_subroutine_dec(&mut self, subroutine_dec: &SubroutineDec) -> Result<()> {
+        let tag_name = "subroutineDec";
+        self.start_node(tag_name, Some(subroutine_dec.span))?;
+        if let Some(symbol_table) = &mut self.symbol_table {
+            symbol_table.enter_subroutine(&subroutine_dec.name.name);
+        }
+        self.write_keyword(subroutine_dec.kind.as_ref())?;
+        match &subroutine_dec.return_type {
+            ReturnType::Void => self.write_keyword("void")?,
+            ReturnType::Type(t) => self.write_type(t)?,
+        }
+        self.write_name(&subroutine_dec.name, SUBROUTINE, DEFINED)?;
+        self.write_symbol("(")?;
+        self.write_parameter_list(&subroutine_dec.parameters)?;
+        self.write_symbol(")")?;
+        self.write_subroutine_body(&subroutine_dec.body)?;
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_parameter_list(&mut self, parameters: &[Parameter]) -> Result<()> {
+        let tag_name = "parameterList";
+        let span = self.empty_list_span(parameters);
+        self.start_node(tag_name, span)?;
+        for (i, parameter) in parameters.iter().enumerate() {
+            if i > 0 {
+                self.write_symbol(",")?;
+            }
+            self.write_type(&parameter.var_type)?;
+            self.write_variable(&parameter.name, DEFINED)?;
+        }
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_subroutine_body(&mut self, body: &SubroutineBody) -> Result<()> {
+        let tag_name = "subroutineBody";
+        self.start_node(tag_name, Some(body.span))?;
+        self.write_symbol("{")?;
+        body.var_decs
+            .iter()
+            .try_for_each(|var_dec| self.write_var_dec(var_dec))?;
+        self.write_statements(&body.statements)?;
+        self.write_symbol("}")?;
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_var_dec(&mut self, var_dec: &VarDec) -> Result<()> {
+        let tag_name = "varDec";
+        self.start_node(tag_name, Some(var_dec.span))?;
+        self.write_keyword("var")?;
+        self.write_type(&var_dec.var_type)?;
+        self.write_var_names(&var_dec.names)?;
+        self.write_symbol(";")?;
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_statements(&mut self, statements: &[Statement]) -> Result<()> {
+        let tag_name = "statements";
+        let span = self.empty_list_span(statements);
+        self.start_node(tag_name, span)?;
+        statements
+            .iter()
+            .try_for_each(|statement| self.write_statement(statement))?;
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Let(let_statement) => self.write_let(let_statement)?,
+            Statement::If(if_statement) => {
+                let tag_name = "ifStatement";
+                self.start_node(tag_name, Some(if_statement.span))?;
+                self.write_keyword("if")?;
+                self.write_condition(&if_statement.condition)?;
+                self.write_block(&if_statement.then_statements)?;
+                if let Some(else_statements) = &if_statement.else_statements {
+                    self.write_keyword("else")?;
+                    self.write_block(else_statements)?;
+                }
+                self.end_node(tag_name)?;
+            }
+            Statement::While(while_statement) => {
+                let tag_name = "whileStatement";
+                self.start_node(tag_name, Some(while_statement.span))?;
+                self.write_keyword("while")?;
+                self.write_condition(&while_statement.condition)?;
+                self.write_block(&while_statement.statements)?;
+                self.end_node(tag_name)?;
+            }
+            Statement::Do(do_statement) => {
+                let tag_name = "doStatement";
+                self.start_node(tag_name, Some(do_statement.span))?;
+                self.write_keyword("do")?;
+                self.write_subroutine_call(&do_statement.call)?;
+                self.write_symbol(";")?;
+                self.end_node(tag_name)?;
+            }
+            Statement::Return(return_statement) => {
+                let tag_name = "returnStatement";
+                self.start_node(tag_name, Some(return_statement.span))?;
+                self.write_keyword("return")?;
+                if let Some(value) = &return_statement.value {
+                    self.write_expression(value)?;
+                }
+                self.write_symbol(";")?;
+                self.end_node(tag_name)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_let(&mut self, let_statement: &LetStatement) -> Result<()> {
+        let tag_name = "letStatement";
+        self.start_node(tag_name, Some(let_statement.span))?;
+        self.write_keyword("let")?;
+        self.write_variable(&let_statement.name, USED)?;
+        if let Some(index) = &let_statement.index {
+            self.write_symbol("[")?;
+            self.write_expression(index)?;
+            self.write_symbol("]")?;
+        }
+        self.write_symbol("=")?;
+        self.write_expression(&let_statement.value)?;
+        self.write_symbol(";")?;
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    // '(' expression ')'
+    fn write_condition(&mut self, condition: &Expression) -> Result<()> {
+        self.write_symbol("(")?;
+        self.write_expression(condition)?;
+        self.write_symbol(")")?;
+        Ok(())
+    }
+
+    // '{' statements '}'
+    fn write_block(&mut self, statements: &[Statement]) -> Result<()> {
+        self.write_symbol("{")?;
+        self.write_statements(statements)?;
+        self.write_symbol("}")?;
+        Ok(())
+    }
+
+    fn write_expression(&mut self, expression: &Expression) -> Result<()> {
+        let tag_name = "expression";
+        self.start_node(tag_name, Some(expression.span))?;
+        self.write_term(&expression.term)?;
+        for (op, term) in &expression.rest {
+            self.write_symbol(op.symbol())?;
+            self.write_term(term)?;
+        }
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_term(&mut self, term: &Term) -> Result<()> {
+        let tag_name = "term";
+        self.start_node(tag_name, Some(term.span()))?;
+        match term {
+            Term::IntegerConstant { value, .. } => {
+                self.write_token("integerConstant", &value.to_string(), Some(term.span()))?;
+            }
+            Term::StringConstant { value, .. } => {
+                self.write_token("stringConstant", value, Some(term.span()))?;
+            }
+            Term::KeywordConstant { keyword, .. } => {
+                self.write_keyword(keyword.as_ref())?;
+            }
+            Term::VarName(name) => {
+                self.write_variable(name, USED)?;
+            }
+            Term::ArrayAccess { name, index, .. } => {
+                self.write_variable(name, USED)?;
+                self.write_symbol("[")?;
+                self.write_expression(index)?;
+                self.write_symbol("]")?;
+            }
+            Term::SubroutineCall(call) => {
+                self.write_subroutine_call(call)?;
+            }
+            Term::Parenthesized { expression, .. } => {
+                self.write_symbol("(")?;
+                self.write_expression(expression)?;
+                self.write_symbol(")")?;
+            }
+            Term::UnaryOp { op, term, .. } => {
+                self.write_symbol(op.symbol())?;
+                self.write_term(term)?;
+            }
+        }
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_subroutine_call(&mut self, call: &SubroutineCall) -> Result<()> {
+        if let Some(receiver) = &call.receiver {
+            self.write_receiver(receiver)?;
+            self.write_symbol(".")?;
+        }
+        self.write_name(&call.name, SUBROUTINE, USED)?;
+        self.write_symbol("(")?;
+        self.write_expression_list(&call.arguments)?;
+        self.write_symbol(")")?;
+        Ok(())
+    }
+
+    fn write_expression_list(&mut self, expressions: &[Expression]) -> Result<()> {
+        let tag_name = "expressionList";
+        let span = self.empty_list_span(expressions);
+        self.start_node(tag_name, span)?;
+        for (i, expression) in expressions.iter().enumerate() {
+            if i > 0 {
+                self.write_symbol(",")?;
+            }
+            self.write_expression(expression)?;
+        }
+        self.end_node(tag_name)?;
+        Ok(())
+    }
+
+    fn write_type(&mut self, var_type: &Type) -> Result<()> {
+        match var_type {
+            Type::ClassName(class_name) => self.write_name(class_name, CLASS, USED),
+            t => self.write_keyword(t.name()),
+        }
+    }
+
+    // varName (',' varName)*
+    fn write_var_names(&mut self, names: &[Identifier]) -> Result<()> {
+        for (i, name) in names.iter().enumerate() {
+            if i > 0 {
+                self.write_symbol(",")?;
+            }
+            self.write_variable(name, DEFINED)?;
+        }
+        Ok(())
+    }
+
+    fn write_keyword(&mut self, keyword: &str) -> Result<()> {
+        self.write_token("keyword", keyword, None)
+    }
+
+    fn write_symbol(&mut self, symbol: &str) -> Result<()> {
+        self.write_token("symbol", symbol, None)
+    }
+
+    fn write_identifier(&mut self, identifier: &Identifier) -> Result<()> {
+        self.write_token("identifier", &identifier.name, Some(identifier.span))
+    }
+
+    fn write_variable(&mut self, identifier: &Identifier, usage: &str) -> Result<()> {
+        let Some(symbol_table) = &self.symbol_table else {
+            return self.write_identifier(identifier);
+        };
+        let attributes = match symbol_table.get(&identifier.name) {
+            Some(symbol) => vec![
+                ("category", symbol.kind.as_ref().to_string()),
+                ("index", symbol.index.to_string()),
+                ("usage", usage.to_string()),
+            ],
+            None => vec![
+                ("category", "undefined".to_string()),
+                ("usage", usage.to_string()),
+            ],
+        };
+        self.write_identifier_with_attributes(identifier, &attributes)
+    }
+
+    // className.subroutineName()とvarName.subroutineName()を区別する
+    fn write_receiver(&mut self, identifier: &Identifier) -> Result<()> {
+        match &self.symbol_table {
+            Some(symbol_table) if symbol_table.get(&identifier.name).is_some() => {
+                self.write_variable(identifier, USED)
+            }
+            _ => self.write_name(identifier, CLASS, USED),
+        }
+    }
+
+    fn write_name(&mut self, identifier: &Identifier, category: &str, usage: &str) -> Result<()> {
+        if self.symbol_table.is_none() {
+            return self.write_identifier(identifier);
+        }
+        self.write_identifier_with_attributes(
+            identifier,
+            &[
+                ("category", category.to_string()),
+                ("usage", usage.to_string()),
+            ],
+        )
+    }
+
+    fn write_identifier_with_attributes(
+        &mut self,
+        identifier: &Identifier,
+        attributes: &[(&str, String)],
+    ) -> Result<()> {
+        let span = self.token_span(Some(identifier.span));
+        self.write(|format, out| {
+            format.token(out, "identifier", &identifier.name, span, attributes)
+        })
+    }
+
+    // トークン列の位置があれば次のトークンの位置を、なければASTの位置を使う
+    fn token_span(&mut self, span: Option<Span>) -> Option<Span> {
+        match self.token_spans.get(self.next_token) {
+            Some(token_span) => {
+                self.next_token += 1;
+                Some(*token_span)
+            }
+            None => span,
+        }
+    }
+
+    // 空のリストは子要素から範囲を求められないので、次のトークンの先頭に幅0の範囲を置く
+    fn empty_list_span<T>(&self, list: &[T]) -> Option<Span> {
+        if !list.is_empty() {
+            return None;
+        }
+        self.token_spans
+            .get(self.next_token)
+            .map(|token_span| Span {
+                end: token_span.start,
+                ..*token_span
+            })
+    }
+
+    fn start_node(&mut self, kind: &str, span: Option<Span>) -> Result<()> {
+        self.write(|format, out| format.start_node(out, kind, span))
+    }
+
+    fn end_node(&mut self, kind: &str) -> Result<()> {
+        self.write(|format, out| format.end_node(out, kind))
+    }
+
+    fn write_token(&mut self, kind: &str, text: &str, span: Option<Span>) -> Result<()> {
+        let span = self.token_span(span);
+        self.write(|format, out| format.token(out, kind, text, span, &[]))
+    }
+
+    fn write(
+        &mut self,
+        write: impl FnOnce(&mut dyn TreeFormat, &mut dyn Write) -> Result<()>,
+    ) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        write(self.format.as_mut(), &mut *writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compilation_engine::CompilationEngine;
+    use jack_tokenizer::{JackTokenizer, TokenStream};
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    fn write_tree(jack_code: &str, format: impl TreeFormat + 'static) -> Result<String> {
+        let tokenizer = JackTokenizer::new(Cursor::new(jack_code.to_string()))?;
+        let class = CompilationEngine::new(tokenizer)?.compile_class()?;
+        let token_spans = TokenStream::new(jack_code.as_bytes())
+            .map(|lexeme| lexeme.map(|lexeme| lexeme.span))
+            .collect::<Result<Vec<_>, _>>()?;
+        let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+        ParseTreeWriter::new(output.clone())
+            .with_format(format)
+            .with_token_spans(token_spans)
+            .write_tree(&class)?;
+        let output = output.lock().unwrap();
+        Ok(String::from_utf8_lossy(output.get_ref()).to_string())
+    }
+
+    fn write_xml(jack_code: &str) -> Result<String> {
+        write_tree(jack_code, XmlFormat)
+    }
+
+    #[test]
+    fn test_write_xml_parameter_list() -> Result<()> {
+        let actual = write_xml("class A { function void f(Point p, int x) { return; } }")?;
+        let expect = "<class>
+<keyword> class </keyword>
+<identifier> A </identifier>
+<symbol> { </symbol>
+<subroutineDec>
+<keyword> function </keyword>
+<keyword> void </keyword>
+<identifier> f </identifier>
+<symbol> ( </symbol>
+<parameterList>
+<identifier> Point </identifier>
+<identifier> p </identifier>
+<symbol> , </symbol>
+<keyword> int </keyword>
+<identifier> x </identifier>
+</parameterList>
+<symbol> ) </symbol>
+<subroutineBody>
+<symbol> { </symbol>
+<statements>
+<returnStatement>
+<keyword> return </keyword>
+<symbol> ; </symbol>
+</returnStatement>
+</statements>
+<symbol> } </symbol>
+</subroutineBody>
+</subroutineDec>
+<symbol> } </symbol>
+</class>
+";
+
+        assert_eq!(expect, actual);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_xml_with_symbol_table() -> Result<()> {
+        let jack_code = "class A {
+    field int x;
+    method void f(A other) {
+        var Array a;
+        let a = other.g(x);
+        do Output.printInt(a[0]);
+        return;
+    }
+}";
+        let tokenizer = JackTokenizer::new(Cursor::new(jack_code))?;
+        let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+        let class = compilation_engine.compile_class()?;
+        let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+        ParseTreeWriter::new(output.clone())
+            .with_symbol_table(compilation_engine.symbol_table().clone())
+            .write_tree(&class)?;
+        let output = output.lock().unwrap();
+        let actual: Vec<String> = String::from_utf8_lossy(output.get_ref())
+            .lines()
+            .filter(|line| line.starts_with("<identifier"))
+            .map(str::to_string)
+            .collect();
+        let expect = vec![
+            r#"<identifier category="class" usage="defined"> A </identifier>"#,
+            r#"<identifier category="field" index="0" usage="defined"> x </identifier>"#,
+            r#"<identifier category="subroutine" usage="defined"> f </identifier>"#,
+            r#"<identifier category="class" usage="used"> A </identifier>"#,
+            r#"<identifier category="argument" index="1" usage="defined"> other </identifier>"#,
+            r#"<identifier category="class" usage="used"> Array </identifier>"#,
+            r#"<identifier category="var" index="0" usage="defined"> a </identifier>"#,
+            r#"<identifier category="var" index="0" usage="used"> a </identifier>"#,
+            r#"<identifier category="argument" index="1" usage="used"> other </identifier>"#,
+            r#"<identifier category="subroutine" usage="used"> g </identifier>"#,
+            r#"<identifier category="field" index="0" usage="used"> x </identifier>"#,
+            r#"<identifier category="class" usage="used"> Output </identifier>"#,
+            r#"<identifier category="subroutine" usage="used"> printInt </identifier>"#,
+            r#"<identifier category="var" index="0" usage="used"> a </identifier>"#,
+        ];
+
+        assert_eq!(expect, actual);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_xml_statements() -> Result<()> {
+        let actual = write_xml(
+            r#"class A {
+    field int x;
+    method void f() {
+        var Array a;
+        let a[1] = ~(x < 2);
+        do Output.printString("hi");
+        return this;
+    }
+}"#,
+        )?;
+        let expect = "<class>
+<keyword> class </keyword>
+<identifier> A </identifier>
+<symbol> { </symbol>
+<classVarDec>
+<keyword> field </keyword>
+<keyword> int </keyword>
+<identifier> x </identifier>
+<symbol> ; </symbol>
+</classVarDec>
+<subroutineDec>
+<keyword> method </keyword>
+<keyword> void </keyword>
+<identifier> f </identifier>
+<symbol> ( </symbol>
+<parameterList>
+</parameterList>
+<symbol> ) </symbol>
+<subroutineBody>
+<symbol> { </symbol>
+<varDec>
+<keyword> var </keyword>
+<identifier> Array </identifier>
+<identifier> a </identifier>
+<symbol> ; </symbol>
+</varDec>
+<statements>
+<letStatement>
+<keyword> let </keyword>
+<identifier> a </identifier>
+<symbol> [ </symbol>
+<expression>
+<term>
+<integerConstant> 1 </integerConstant>
+</term>
+</expression>
+<symbol> ] </symbol>
+<symbol> = </symbol>
+<expression>
+<term>
+<symbol> ~ </symbol>
+<term>
+<symbol> ( </symbol>
+<expression>
+<term>
+<identifier> x </identifier>
+</term>
+<symbol> &lt; </symbol>
+<term>
+<integerConstant> 2 </integerConstant>
+</term>
+</expression>
+<symbol> ) </symbol>
+</term>
+</term>
+</expression>
+<symbol> ; </symbol>
+</letStatement>
+<doStatement>
+<keyword> do </keyword>
+<identifier> Output </identifier>
+<symbol> . </symbol>
+<identifier> printString </identifier>
+<symbol> ( </symbol>
+<expressionList>
+<expression>
+<term>
+<stringConstant> hi </stringConstant>
+</term>
+</expression>
+</expressionList>
+<symbol> ) </symbol>
+<symbol> ; </symbol>
+</doStatement>
+<returnStatement>
+<keyword> return </keyword>
+<expression>
+<term>
+<keyword> this </keyword>
+</term>
+</expression>
+<symbol> ; </symbol>
+</returnStatement>
+</statements>
+<symbol> } </symbol>
+</subroutineBody>
+</subroutineDec>
+<symbol> } </symbol>
+</class>
+";
+
+        assert_eq!(expect, actual);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_json() -> Result<()> {
+        let actual = write_tree("class A { field int x; }", JsonFormat::default())?;
+        let actual: Value = serde_json::from_str(&actual)?;
+        let span =
+            |start, end, column| json!({"start": start, "end": end, "line": 1, "column": column});
+        let expect = json!({
+            "kind": "class",
+            "span": span(0, 24, 1),
+            "children": [
+                {"kind": "keyword", "text": "class", "span": span(0, 5, 1)},
+                {"kind": "identifier", "text": "A", "span": span(6, 7, 7)},
+                {"kind": "symbol", "text": "{", "span": span(8, 9, 9)},
+                {
+                    "kind": "classVarDec",
+                    "span": span(10, 22, 11),
+                    "children": [
+                        {"kind": "keyword", "text": "field", "span": span(10, 15, 11)},
+                        {"kind": "keyword", "text": "int", "span": span(16, 19, 17)},
+                        {"kind": "identifier", "text": "x", "span": span(20, 21, 21)},
+                        {"kind": "symbol", "text": ";", "span": span(21, 22, 22)}
+                    ]
+                },
+                {"kind": "symbol", "text": "}", "span": span(23, 24, 24)}
+            ]
+        });
+
+        assert_eq!(expect, actual);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_json_spans() -> Result<()> {
+        let actual = write_tree(
+            r#"class A {
+    method void f() { }
+    function int g(int x, A a) {
+        var String s;
+        let s = "é";
+        do a.f();
+        if (~(x < 0)) { return -x; } else { }
+        return Math.max(x, 1);
+    }
+}"#,
+            JsonFormat::default(),
+        )?;
+        let actual: Value = serde_json::from_str(&actual)?;
+
+        fn find_null_spans(value: &Value, kinds: &mut Vec<String>) {
+            if value["span"].is_null() {
+                kinds.push(value["kind"].to_string());
+            }
+            if let Some(children) = value["children"].as_array() {
+                children
+                    .iter()
+                    .for_each(|child| find_null_spans(child, kinds));
+            }
+        }
+        let mut kinds = Vec::new();
+        find_null_spans(&actual, &mut kinds);
+        assert_eq!(kinds, Vec::<String>::new());
+
+        // 空のparameterListは")"の先頭に幅0の範囲を持つ
+        let parameter_list = &actual["children"][3]["children"][4];
+        assert_eq!(parameter_list["kind"], "parameterList");
+        assert_eq!(
+            parameter_list["span"],
+            json!({"start": 28, "end": 28, "line": 2, "column": 19})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_sexp() -> Result<()> {
+        let actual = write_tree(
+            r#"class A { function void f() { do Output.printString("a<b"); return; } }"#,
+            SexpFormat::default(),
+        )?;
+        let expect = r#"(class
+  (keyword "class")
+  (identifier "A")
+  (symbol "{")
+  (subroutineDec
+    (keyword "function")
+    (keyword "void")
+    (identifier "f")
+    (symbol "(")
+    (parameterList)
+    (symbol ")")
+    (subroutineBody
+      (symbol "{")
+      (statements
+        (doStatement
+          (keyword "do")
+          (identifier "Output")
+          (symbol ".")
+          (identifier "printString")
+          (symbol "(")
+          (expressionList
+            (expression
+              (term
+                (stringConstant "a<b"))))
+          (symbol ")")
+          (symbol ";"))
+        (returnStatement
+          (keyword "return")
+          (symbol ";")))
+      (symbol "}")))
+  (symbol "}"))
+"#;
+
+        assert_eq!(expect, actual);
+        Ok(())
+    }
+}
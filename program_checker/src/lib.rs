@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+
+use compilation_engine::CompilationEngine;
+use diagnostics::Diagnostic;
+use jack_ast::{Class, Expression, Statement, SubroutineCall, SubroutineKind, Term};
+use jack_tokenizer::JackTokenizer;
+use symbol_table::SymbolTable;
+use type_checker::Signature;
+
+// Jack OSの標準クラスのAPI。本体は検査に使わないので空にしている
+const OS_CLASSES: [&str; 8] = [
+    "class Math {
+    function void init() {}
+    function int abs(int x) {}
+    function int multiply(int x, int y) {}
+    function int divide(int x, int y) {}
+    function int min(int x, int y) {}
+    function int max(int x, int y) {}
+    function int sqrt(int x) {}
+}",
+    "class String {
+    constructor String new(int maxLength) {}
+    method void dispose() {}
+    method int length() {}
+    method char charAt(int j) {}
+    method void setCharAt(int j, char c) {}
+    method String appendChar(char c) {}
+    method void eraseLastChar() {}
+    method int intValue() {}
+    method void setInt(int j) {}
+    function char backSpace() {}
+    function char doubleQuote() {}
+    function char newLine() {}
+}",
+    "class Array {
+    function Array new(int size) {}
+    method void dispose() {}
+}",
+    "class Output {
+    function void init() {}
+    function void moveCursor(int i, int j) {}
+    function void printChar(char c) {}
+    function void printString(String s) {}
+    function void printInt(int i) {}
+    function void println() {}
+    function void backSpace() {}
+}",
+    "class Screen {
+    function void init() {}
+    function void clearScreen() {}
+    function void setColor(boolean b) {}
+    function void drawPixel(int x, int y) {}
+    function void drawLine(int x1, int y1, int x2, int y2) {}
+    function void drawRectangle(int x1, int y1, int x2, int y2) {}
+    function void drawCircle(int x, int y, int r) {}
+}",
+    "class Keyboard {
+    function void init() {}
+    function char keyPressed() {}
+    function char readChar() {}
+    function String readLine(String message) {}
+    function int readInt(String message) {}
+}",
+    "class Memory {
+    function void init() {}
+    function int peek(int address) {}
+    function void poke(int address, int value) {}
+    function Array alloc(int size) {}
+    function void deAlloc(Array o) {}
+}",
+    "class Sys {
+    function void init() {}
+    function void halt() {}
+    function void error(int errorCode) {}
+    function void wait(int duration) {}
+}",
+];
+
+// ディレクトリ内のすべてのクラスとJack OSのサブルーチンのシグネチャを保持する
+#[derive(Debug, Clone)]
+pub struct Program {
+    // クラス名ごとのサブルーチン名とシグネチャ
+    classes: HashMap<String, HashMap<String, Signature>>,
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Program {
+    pub fn new() -> Self {
+        let mut program = Self {
+            classes: HashMap::new(),
+        };
+        for source in OS_CLASSES {
+            let tokenizer = JackTokenizer::new(source.as_bytes()).unwrap();
+            let class = CompilationEngine::new(tokenizer)
+                .and_then(|mut compilation_engine| compilation_engine.compile_class())
+                .unwrap_or_else(|e| panic!("invalid os class: {}", e));
+            program.add_class(&class);
+        }
+        program
+    }
+
+    // 同名のクラスはあとから追加したもので置き換える
+    pub fn add_class(&mut self, class: &Class) {
+        self.classes.insert(
+            class.name.name.clone(),
+            class
+                .subroutine_decs
+                .iter()
+                .map(|subroutine_dec| {
+                    (
+                        subroutine_dec.name.name.clone(),
+                        Signature::new(subroutine_dec),
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    pub fn has_class(&self, class_name: &str) -> bool {
+        self.classes.contains_key(class_name)
+    }
+
+    pub fn signature(&self, class_name: &str, subroutine_name: &str) -> Option<&Signature> {
+        self.classes
+            .get(class_name)
+            .and_then(|subroutines| subroutines.get(subroutine_name))
+    }
+
+    // classのサブルーチン呼び出しをプログラム全体のシグネチャと照合する。
+    // symbol_tableはCompilationEngineが構文解析時に作成したものを受け取る
+    pub fn check(&self, class: &Class, symbol_table: SymbolTable) -> Vec<Diagnostic> {
+        let mut checker = CallChecker {
+            program: self,
+            symbol_table,
+            class_name: class.name.name.clone(),
+            subroutine_kind: SubroutineKind::Function,
+            diagnostics: Vec::new(),
+        };
+        for subroutine_dec in &class.subroutine_decs {
+            if checker
+                .symbol_table
+                .enter_subroutine(&subroutine_dec.name.name)
+            {
+                checker.subroutine_kind = subroutine_dec.kind;
+                checker.check_statements(&subroutine_dec.body.statements);
+            }
+        }
+        checker.diagnostics
+    }
+}
+
+struct CallChecker<'a> {
+    program: &'a Program,
+    symbol_table: SymbolTable,
+    class_name: String,
+    // 呼び出し元のサブルーチンの種類。functionにはthisがない
+    subroutine_kind: SubroutineKind,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl CallChecker<'_> {
+    fn check_statements(&mut self, statements: &[Statement]) {
+        statements
+            .iter()
+            .for_each(|statement| self.check_statement(statement));
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let(let_statement) => {
+                if let Some(index) = &let_statement.index {
+                    self.check_expression(index);
+                }
+                self.check_expression(&let_statement.value);
+            }
+            Statement::If(if_statement) => {
+                self.check_expression(&if_statement.condition);
+                self.check_statements(&if_statement.then_statements);
+                if let Some(else_statements) = &if_statement.else_statements {
+                    self.check_statements(else_statements);
+                }
+            }
+            Statement::While(while_statement) => {
+                self.check_expression(&while_statement.condition);
+                self.check_statements(&while_statement.statements);
+            }
+            Statement::Do(do_statement) => self.check_subroutine_call(&do_statement.call),
+            Statement::Return(return_statement) => {
+                if let Some(value) = &return_statement.value {
+                    self.check_expression(value);
+                }
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) {
+        self.check_term(&expression.term);
+        for (_, term) in &expression.rest {
+            self.check_term(term);
+        }
+    }
+
+    fn check_term(&mut self, term: &Term) {
+        match term {
+            Term::ArrayAccess { index, .. } => self.check_expression(index),
+            Term::SubroutineCall(call) => self.check_subroutine_call(call),
+            Term::Parenthesized { expression, .. } => self.check_expression(expression),
+            Term::UnaryOp { term, .. } => self.check_term(term),
+            Term::IntegerConstant { .. }
+            | Term::StringConstant { .. }
+            | Term::KeywordConstant { .. }
+            | Term::VarName(_) => (),
+        }
+    }
+
+    fn check_subroutine_call(&mut self, call: &SubroutineCall) {
+        call.arguments
+            .iter()
+            .for_each(|argument| self.check_expression(argument));
+
+        // (呼び出し先のクラス名, オブジェクトに対する呼び出しか)
+        let (class_name, on_object) = match &call.receiver {
+            None => (self.class_name.clone(), true),
+            Some(receiver) => match self.symbol_table.type_of(&receiver.name) {
+                // int等に対する呼び出しはTypeCheckerが報告する
+                Some("int" | "char" | "boolean") => return,
+                Some(var_type) => (var_type.to_string(), true),
+                None => (receiver.name.clone(), false),
+            },
+        };
+        if !self.program.has_class(&class_name) {
+            let span = call.receiver.as_ref().map_or(call.span, |r| r.span);
+            self.diagnostics.push(
+                Diagnostic::error(format!("undefined class: {:?}", class_name), span)
+                    .with_code("undefined-class"),
+            );
+            return;
+        }
+        let full_name = format!("{}.{}", class_name, call.name.name);
+        let Some(signature) = self.program.signature(&class_name, &call.name.name) else {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!("undefined subroutine: {:?}", full_name),
+                    call.name.span,
+                )
+                .with_code("undefined-subroutine"),
+            );
+            return;
+        };
+        match (signature.kind, on_object) {
+            (SubroutineKind::Method, false) => {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("method called as a function: {:?}", full_name),
+                        call.span,
+                    )
+                    .with_code("method-as-function")
+                    .with_note(format!("call it on a {:?} object", class_name)),
+                );
+            }
+            (SubroutineKind::Function | SubroutineKind::Constructor, true) => {
+                let diagnostic = Diagnostic::error(
+                    format!(
+                        "{} called as a method: {:?}",
+                        signature.kind.as_ref(),
+                        full_name
+                    ),
+                    call.span,
+                )
+                .with_code("function-as-method");
+                // 変数に対する呼び出しはクラス名に置き換えれば呼び出せる
+                let diagnostic = match &call.receiver {
+                    Some(receiver) => diagnostic.with_suggestion(
+                        format!("call it on the class {:?}", class_name),
+                        class_name.clone(),
+                        receiver.span,
+                    ),
+                    None => diagnostic,
+                };
+                self.diagnostics.push(diagnostic);
+            }
+            // functionの中ではthisがないので自クラスのmethodを直接呼び出せない
+            (SubroutineKind::Method, true)
+                if call.receiver.is_none() && self.subroutine_kind == SubroutineKind::Function =>
+            {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("method called from a function: {:?}", full_name),
+                        call.span,
+                    )
+                    .with_code("method-from-function")
+                    .with_note("a function has no \"this\" to call the method on"),
+                );
+            }
+            _ => (),
+        }
+        if signature.parameters.len() != call.arguments.len() {
+            let parameters = signature
+                .parameters
+                .iter()
+                .map(|parameter| parameter.name())
+                .collect::<Vec<_>>();
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!(
+                        "wrong number of arguments to {:?}: expected {}, found {}",
+                        full_name,
+                        signature.parameters.len(),
+                        call.arguments.len()
+                    ),
+                    call.span,
+                )
+                .with_code("wrong-argument-count")
+                .with_note(format!(
+                    "{:?} takes ({})",
+                    full_name,
+                    parameters.join(", ")
+                )),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+    use type_checker::JackType;
+
+    fn parse(jack_code: &str) -> Result<(Class, SymbolTable)> {
+        let tokenizer = JackTokenizer::new(Cursor::new(jack_code.to_string()))?;
+        let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+        let class = compilation_engine.compile_class()?;
+        Ok((class, compilation_engine.symbol_table().clone()))
+    }
+
+    fn check(jack_codes: &[&str]) -> Result<Vec<String>> {
+        let classes = jack_codes
+            .iter()
+            .map(|jack_code| parse(jack_code))
+            .collect::<Result<Vec<_>>>()?;
+        let mut program = Program::new();
+        classes
+            .iter()
+            .for_each(|(class, _)| program.add_class(class));
+        let (class, symbol_table) = classes.into_iter().next().unwrap();
+        Ok(program
+            .check(&class, symbol_table)
+            .iter()
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    const GAME: &str = "class Game {
+    field int score;
+    constructor Game new() { return this; }
+    method void run(int n) { do draw(); return; }
+    method void draw() { return; }
+    function int best() { return 0; }
+}";
+
+    #[test]
+    fn test_check_when_valid() -> Result<()> {
+        let diagnostics = check(&[
+            "class Main {
+    function void main() {
+        var Game game;
+        var String s;
+        let game = Game.new();
+        let s = String.new(Game.best());
+        do game.run(s.length());
+        do Output.printString(s.appendChar(65));
+        do Memory.deAlloc(game);
+        return;
+    }
+}",
+            GAME,
+        ])?;
+
+        assert_eq!(diagnostics, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_when_invalid_calls() -> Result<()> {
+        let diagnostics = check(&[
+            "class Main {
+    function void main() {
+        var Game game;
+        do Game.start();
+        do Player.new();
+        do game.run();
+        do Game.run(1);
+        do game.best();
+        do draw();
+        do Math.max(1);
+        return;
+    }
+    method void draw() { return; }
+}",
+            GAME,
+        ])?;
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                "4:17: undefined subroutine: \"Game.start\"",
+                "5:12: undefined class: \"Player\"",
+                "6:12: wrong number of arguments to \"Game.run\": expected 1, found 0",
+                "7:12: method called as a function: \"Game.run\"",
+                "8:12: function called as a method: \"Game.best\"",
+                "9:12: method called from a function: \"Main.draw\"",
+                "10:12: wrong number of arguments to \"Math.max\": expected 2, found 1",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_os_classes() {
+        let program = Program::new();
+        for class_name in [
+            "Math", "String", "Array", "Output", "Screen", "Keyboard", "Memory", "Sys",
+        ] {
+            assert!(program.has_class(class_name), "{}", class_name);
+        }
+        let signature = program.signature("String", "new").unwrap();
+        assert_eq!(signature.kind, SubroutineKind::Constructor);
+        assert_eq!(signature.parameters, vec![JackType::Int]);
+    }
+}
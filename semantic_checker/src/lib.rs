@@ -0,0 +1,329 @@
+use std::collections::HashSet;
+
+use diagnostics::Diagnostic;
+use jack_ast::{
+    is_jack_character, Class, ClassVarKind, Expression, Identifier, Statement, SubroutineCall,
+    SubroutineDec, Term, Type,
+};
+use jack_tokenizer::Span;
+use symbol_table::{Kind, SymbolTable};
+
+// 構文解析済みのクラスに対して宣言と参照の整合性を検査する。
+// 宣言はCompilationEngineと同じ順序でシンボルテーブルに登録する
+#[derive(Debug, Default)]
+pub struct SemanticChecker {
+    symbol_table: SymbolTable,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl SemanticChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 検査の結果をソース上の出現順に返す
+    pub fn check(mut self, class: &Class) -> Vec<Diagnostic> {
+        for class_var_dec in &class.class_var_decs {
+            let kind = match class_var_dec.kind {
+                ClassVarKind::Static => Kind::Static,
+                ClassVarKind::Field => Kind::Field,
+            };
+            for name in &class_var_dec.names {
+                self.define(name, &class_var_dec.var_type, kind);
+            }
+        }
+        let mut subroutine_names = HashSet::new();
+        for subroutine_dec in &class.subroutine_decs {
+            if !subroutine_names.insert(subroutine_dec.name.name.as_str()) {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!(
+                            "duplicate subroutine declaration: {:?}",
+                            subroutine_dec.name.name
+                        ),
+                        subroutine_dec.name.span,
+                    )
+                    .with_code("duplicate-subroutine"),
+                );
+            }
+            self.check_subroutine(subroutine_dec);
+        }
+        self.diagnostics
+    }
+
+    fn check_subroutine(&mut self, subroutine_dec: &SubroutineDec) {
+        self.symbol_table
+            .start_subroutine(&subroutine_dec.name.name);
+        for parameter in &subroutine_dec.parameters {
+            self.define(&parameter.name, &parameter.var_type, Kind::Arg);
+        }
+        for var_dec in &subroutine_dec.body.var_decs {
+            for name in &var_dec.names {
+                self.define(name, &var_dec.var_type, Kind::Var);
+            }
+        }
+        self.check_statements(&subroutine_dec.body.statements);
+    }
+
+    fn check_statements(&mut self, statements: &[Statement]) {
+        statements
+            .iter()
+            .for_each(|statement| self.check_statement(statement));
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let(let_statement) => {
+                match &let_statement.index {
+                    // arr[i] = expression
+                    Some(index) => {
+                        if self.symbol_table.get(&let_statement.name.name).is_none() {
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    format!(
+                                        "assignment to undeclared array: {:?}",
+                                        let_statement.name.name
+                                    ),
+                                    let_statement.name.span,
+                                )
+                                .with_code("undeclared-variable"),
+                            );
+                        }
+                        self.check_expression(index);
+                    }
+                    None => self.check_variable(&let_statement.name),
+                }
+                self.check_expression(&let_statement.value);
+            }
+            Statement::If(if_statement) => {
+                self.check_expression(&if_statement.condition);
+                self.check_statements(&if_statement.then_statements);
+                if let Some(else_statements) = &if_statement.else_statements {
+                    self.check_statements(else_statements);
+                }
+            }
+            Statement::While(while_statement) => {
+                self.check_expression(&while_statement.condition);
+                self.check_statements(&while_statement.statements);
+            }
+            Statement::Do(do_statement) => self.check_subroutine_call(&do_statement.call),
+            Statement::Return(return_statement) => {
+                if let Some(value) = &return_statement.value {
+                    self.check_expression(value);
+                }
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) {
+        self.check_term(&expression.term);
+        for (_, term) in &expression.rest {
+            self.check_term(term);
+        }
+    }
+
+    fn check_term(&mut self, term: &Term) {
+        match term {
+            Term::VarName(name) => self.check_variable(name),
+            Term::ArrayAccess { name, index, .. } => {
+                self.check_variable(name);
+                self.check_expression(index);
+            }
+            Term::SubroutineCall(call) => self.check_subroutine_call(call),
+            Term::Parenthesized { expression, .. } => self.check_expression(expression),
+            Term::UnaryOp { term, .. } => self.check_term(term),
+            Term::StringConstant { value, span } => self.check_string_constant(value, *span),
+            Term::IntegerConstant { .. } | Term::KeywordConstant { .. } => (),
+        }
+    }
+
+    // Jackの文字セット以外の文字は文字コードを正しく出力できないので、文字ごとにエラーにする
+    fn check_string_constant(&mut self, value: &str, span: Span) {
+        // spanは先頭の'"'を指す
+        for (column, (offset, c)) in value.char_indices().enumerate() {
+            if is_jack_character(c) {
+                continue;
+            }
+            let start = span.start + 1 + offset;
+            let char_span = Span {
+                start,
+                end: start + c.len_utf8(),
+                column: span.column + 1 + column,
+                ..span
+            };
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!("unsupported character in string constant: {:?}", c),
+                    char_span,
+                )
+                .with_code("unsupported-character")
+                .with_note("Jack strings can only contain printable ASCII characters"),
+            );
+        }
+    }
+
+    // receiverは未宣言であればクラス名とみなすので検査しない
+    fn check_subroutine_call(&mut self, call: &SubroutineCall) {
+        call.arguments
+            .iter()
+            .for_each(|argument| self.check_expression(argument));
+    }
+
+    fn check_variable(&mut self, name: &Identifier) {
+        if self.symbol_table.get(&name.name).is_none() {
+            self.diagnostics.push(
+                Diagnostic::error(format!("undeclared variable: {:?}", name.name), name.span)
+                    .with_code("undeclared-variable"),
+            );
+        }
+    }
+
+    // 同じスコープでの重複はエラー、サブルーチンスコープでクラス変数を隠す場合は警告にする
+    fn define(&mut self, name: &Identifier, var_type: &Type, kind: Kind) {
+        let shadowed = self
+            .symbol_table
+            .kind_of(&name.name)
+            .filter(|shadowed| !kind.is_class_scope() && shadowed.is_class_scope());
+        if !self.symbol_table.define(&name.name, var_type.name(), kind) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("duplicate declaration: {:?}", name.name), name.span)
+                    .with_code("duplicate-declaration"),
+            );
+        } else if let Some(shadowed) = shadowed {
+            self.diagnostics.push(
+                Diagnostic::warning(
+                    format!(
+                        "{} shadows {}: {:?}",
+                        kind.as_ref(),
+                        shadowed.as_ref(),
+                        name.name
+                    ),
+                    name.span,
+                )
+                .with_code("shadowed-variable")
+                .with_note(format!(
+                    "the {} {:?} cannot be accessed in this subroutine",
+                    shadowed.as_ref(),
+                    name.name
+                )),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use compilation_engine::CompilationEngine;
+    use jack_tokenizer::JackTokenizer;
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    fn check(jack_code: &str) -> Result<Vec<String>> {
+        let tokenizer = JackTokenizer::new(Cursor::new(jack_code.to_string()))?;
+        let class = CompilationEngine::new(tokenizer)?.compile_class()?;
+        Ok(SemanticChecker::new()
+            .check(&class)
+            .iter()
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    #[test]
+    fn test_check_when_valid() -> Result<()> {
+        let diagnostics = check(
+            "class Point {
+    field int x, y;
+    static Array cache;
+    method int sum(Point other) {
+        var int i;
+        let cache[i] = other.sum(this) + Math.abs(x);
+        while (i < y) { let i = i + cache[i]; }
+        return -i;
+    }
+}",
+        )?;
+
+        assert_eq!(diagnostics, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_when_undeclared() -> Result<()> {
+        let diagnostics = check(
+            "class Main {
+    function void main() {
+        let x = y;
+        let a[0] = 1;
+        if (b[z]) { do Output.printInt(c); }
+        return;
+    }
+}",
+        )?;
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                "3:13: undeclared variable: \"x\"",
+                "3:17: undeclared variable: \"y\"",
+                "4:13: assignment to undeclared array: \"a\"",
+                "5:13: undeclared variable: \"b\"",
+                "5:15: undeclared variable: \"z\"",
+                "5:40: undeclared variable: \"c\"",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_when_unsupported_character() -> Result<()> {
+        let diagnostics = check(
+            r#"class Main {
+    function void main() {
+        do Output.printString("café 😀!");
+        return;
+    }
+}"#,
+        )?;
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                "3:35: unsupported character in string constant: 'é'",
+                "3:37: unsupported character in string constant: '😀'",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_when_duplicate_and_shadowing() -> Result<()> {
+        let diagnostics = check(
+            "class Main {
+    field int x;
+    static int x;
+    method void run(int x, int a) {
+        var int a, b, b;
+        return;
+    }
+    function void run() {
+        var int b;
+        return;
+    }
+}",
+        )?;
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                "3:16: duplicate declaration: \"x\"",
+                "4:25: warning: argument shadows field: \"x\"",
+                "5:17: duplicate declaration: \"a\"",
+                "5:23: duplicate declaration: \"b\"",
+                "8:19: duplicate subroutine declaration: \"run\"",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use diagnostics_writer::DiagnosticFormat;
+use type_checker::Strictness;
+
+use crate::Emit;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "JackAnalyzer",
+    version,
+    about = "Tokenizer, parser and compiler for the nand2tetris Jack language"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Write the token stream of each .jack file as XxxT.xml
+    Tokenize {
+        #[command(flatten)]
+        common: CommonArgs,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = TokenizeFormat::Xml)]
+        format: TokenizeFormat,
+    },
+    /// Write the parse tree of each .jack file as Xxx.xml (xml also writes XxxT.xml)
+    Parse {
+        #[command(flatten)]
+        common: CommonArgs,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ParseFormat::Xml)]
+        format: ParseFormat,
+    },
+    /// Report diagnostics without writing any output files
+    Check {
+        #[command(flatten)]
+        common: CommonArgs,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = CheckFormat::Text)]
+        format: CheckFormat,
+        #[command(flatten)]
+        type_check: TypeCheckArgs,
+    },
+    /// Compile each .jack file to Xxx.vm
+    Compile {
+        #[command(flatten)]
+        common: CommonArgs,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = CompileFormat::Vm)]
+        format: CompileFormat,
+        #[command(flatten)]
+        type_check: TypeCheckArgs,
+    },
+    /// Report style and correctness lints (configured by .jacklint in the target directory)
+    Lint {
+        #[command(flatten)]
+        common: CommonArgs,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = LintFormat::Text)]
+        format: LintFormat,
+    },
+    /// Format Jack source code
+    Fmt {
+        #[command(flatten)]
+        common: CommonArgs,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = FmtFormat::Jack)]
+        format: FmtFormat,
+        /// Do not rewrite files; exit with an error if any file is not formatted
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct CommonArgs {
+    /// A .jack file or a directory containing .jack files
+    #[arg(default_value = "./", conflicts_with = "stdin")]
+    pub path: String,
+    /// Directory to write output files to instead of next to the sources
+    #[arg(long, conflicts_with = "stdin")]
+    pub out_dir: Option<PathBuf>,
+    /// Do not print the files that were written
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// Read a single Jack source from stdin and write the result to stdout.
+    /// Only `tokenize` streams the input; other subcommands read it all into memory first
+    #[arg(long)]
+    pub stdin: bool,
+    /// Also analyze .jack files in subdirectories
+    #[arg(short, long)]
+    pub recursive: bool,
+    /// Only analyze files whose path relative to PATH matches the glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// Skip files and directories whose path relative to PATH matches the glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+    /// Number of files to analyze in parallel (defaults to the number of CPUs)
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct TypeCheckArgs {
+    /// Also check the types of expressions, reporting mismatches as warnings or errors
+    #[arg(
+        long,
+        value_enum,
+        value_name = "LEVEL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "warn"
+    )]
+    pub type_check: Option<TypeCheckLevel>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TypeCheckLevel {
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TokenizeFormat {
+    Xml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ParseFormat {
+    Xml,
+    // <identifier>にシンボルテーブルの情報を付与したxml(11章)
+    ExtendedXml,
+    // ノードの種類、子要素、位置を持つJSON
+    Json,
+    // S式
+    Sexp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum CheckFormat {
+    Text,
+    // 1行に1つの診断を書くJSON Lines
+    Json,
+    // SARIF 2.1.0
+    Sarif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum CompileFormat {
+    Vm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum LintFormat {
+    Text,
+    Json,
+    Sarif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum FmtFormat {
+    Jack,
+}
+
+impl Command {
+    pub fn common(&self) -> &CommonArgs {
+        match self {
+            Command::Tokenize { common, .. }
+            | Command::Parse { common, .. }
+            | Command::Check { common, .. }
+            | Command::Compile { common, .. }
+            | Command::Lint { common, .. }
+            | Command::Fmt { common, .. } => common,
+        }
+    }
+
+    pub fn emit(&self) -> Result<Emit> {
+        match self {
+            Command::Tokenize {
+                format: TokenizeFormat::Xml,
+                ..
+            } => Ok(Emit::Tokens),
+            Command::Parse {
+                format: ParseFormat::Xml,
+                ..
+            } => Ok(Emit::Xml),
+            Command::Parse {
+                format: ParseFormat::ExtendedXml,
+                ..
+            } => Ok(Emit::ExtendedXml),
+            Command::Parse {
+                format: ParseFormat::Json,
+                ..
+            } => Ok(Emit::Json),
+            Command::Parse {
+                format: ParseFormat::Sexp,
+                ..
+            } => Ok(Emit::Sexp),
+            Command::Check { .. } => Ok(Emit::Check),
+            Command::Compile {
+                format: CompileFormat::Vm,
+                ..
+            } => Ok(Emit::Vm),
+            Command::Lint { .. } => Ok(Emit::Lint),
+            Command::Fmt {
+                format: FmtFormat::Jack,
+                ..
+            } => Ok(Emit::Fmt),
+        }
+    }
+
+    pub fn check(&self) -> bool {
+        matches!(self, Command::Fmt { check: true, .. })
+    }
+
+    // checkとlintは診断そのものが出力なので形式を選べる。それ以外はテキストで報告する
+    pub fn diagnostic_format(&self) -> DiagnosticFormat {
+        match self {
+            Command::Check {
+                format: CheckFormat::Json,
+                ..
+            }
+            | Command::Lint {
+                format: LintFormat::Json,
+                ..
+            } => DiagnosticFormat::Json,
+            Command::Check {
+                format: CheckFormat::Sarif,
+                ..
+            }
+            | Command::Lint {
+                format: LintFormat::Sarif,
+                ..
+            } => DiagnosticFormat::Sarif,
+            _ => DiagnosticFormat::Text,
+        }
+    }
+
+    pub fn type_check(&self) -> Option<Strictness> {
+        match self {
+            Command::Check { type_check, .. } | Command::Compile { type_check, .. } => {
+                type_check.type_check.map(|level| match level {
+                    TypeCheckLevel::Warn => Strictness::Warn,
+                    TypeCheckLevel::Error => Strictness::Error,
+                })
+            }
+            _ => None,
+        }
+    }
+}
@@ -1,45 +1,242 @@
+mod cli;
+
 use std::{
-    fs::File,
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io::{self, Cursor, IsTerminal, Read, Write},
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
 use anyhow::{anyhow, Result};
+use clap::Parser;
+use cli::Cli;
+use code_generator::CodeGenerator;
 use compilation_engine::CompilationEngine;
-use jack_tokenizer::JackTokenizer;
+use diagnostics::{Diagnostic, Diagnostics, Severity};
+use diagnostics_writer::{DiagnosticFormat, DiagnosticsWriter};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use jack_formatter::JackFormatter;
+use jack_lint::{LintConfig, Linter};
+use jack_tokenizer::{JackTokenizer, LexError, Span, TokenStream};
+use parse_tree_writer::{JsonFormat, ParseTreeWriter, SexpFormat};
+use program_checker::Program;
+use rayon::{prelude::*, ThreadPoolBuilder};
+use semantic_checker::SemanticChecker;
+use tokenized_xml_writer::TokenizedXmlWriter;
+use type_checker::{Strictness, TypeChecker};
+use vm_writer::VMWriter;
+use walkdir::WalkDir;
 
 const JACK_FILE_EXTENSION: &str = "jack";
-const OUTPUT_FILE_EXTENSION: &str = "xml";
+const JACK_IGNORE_FILE: &str = ".jackignore";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Emit {
+    Tokens,
+    Xml,
+    ExtendedXml,
+    // 構文木のJSON
+    Json,
+    // 構文木のS式
+    Sexp,
+    Vm,
+    // 診断のみでファイルは出力しない
+    Check,
+    // 整形したソースで元の.jackファイルを書き換える
+    Fmt,
+    // lintの指摘のみでファイルは出力しない
+    Lint,
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    out_dir: Option<PathBuf>,
+    quiet: bool,
+    recursive: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    jobs: usize,
+    // fmtで書き換えずに整形済みかどうかだけを確認する
+    check: bool,
+    // 指定された場合は意味解析に加えて型検査を行う
+    type_check: Option<Strictness>,
+    lint_config: LintConfig,
+    diagnostic_format: DiagnosticFormat,
+}
+
+// 1つの.jackファイルから生成される出力。ファイル名は"{stem}{suffix}.{extension}"
+#[derive(Debug)]
+struct Output {
+    suffix: &'static str,
+    extension: &'static str,
+    content: Vec<u8>,
+}
 
-fn main() -> Result<()> {
-    if let Err(e) = jack_analyzer(&parse_arg(std::env::args().collect())?) {
+// 1つの.jackファイルの解析結果。警告だけであれば出力は生成する
+#[derive(Debug, Default)]
+struct Analysis {
+    outputs: Vec<Output>,
+    warnings: Vec<Diagnostic>,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(&cli.command) {
         eprintln!("{}", e);
         std::process::exit(1);
     }
-    Ok(())
 }
 
-fn parse_arg(args: Vec<String>) -> Result<String> {
-    let current_dir = "./".to_string();
-    match args.get(1) {
-        Some(arg) if arg.is_empty() => Ok(current_dir),
-        Some(arg) => Ok(arg.to_string()),
-        _ => Ok(current_dir),
+fn run(command: &cli::Command) -> Result<()> {
+    let common = command.common();
+    let emit = command.emit()?;
+    let diagnostic_format = command.diagnostic_format();
+    let options = Options {
+        out_dir: common.out_dir.clone(),
+        // 機械可読な診断を標準出力に書くので、ファイル一覧等は出力しない
+        quiet: common.quiet || diagnostic_format != DiagnosticFormat::Text,
+        recursive: common.recursive,
+        include: common.include.clone(),
+        exclude: common.exclude.clone(),
+        jobs: common.jobs.unwrap_or_default(),
+        check: command.check(),
+        type_check: command.type_check(),
+        lint_config: if emit == Emit::Lint {
+            read_lint_config(&config_dir(common))?
+        } else {
+            LintConfig::default()
+        },
+        diagnostic_format,
+    };
+    if common.stdin && emit == Emit::Tokens {
+        // トークンの出力は入力全体を読み込まずに1行ずつ処理する。
+        // 字句エラーの手前までのトークンを出力してからエラーを報告する
+        let mut lex_error = None;
+        let tokens = TokenStream::new(io::stdin().lock())
+            .map_while(|lexeme| lexeme.map_err(|e| lex_error = Some(e)).ok())
+            .map(|lexeme| lexeme.token);
+        TokenizedXmlWriter::new(Arc::new(Mutex::new(io::stdout())))
+            .write_xml(tokens)
+            .map_err(|e| with_path("<stdin>", e))?;
+        return match lex_error {
+            Some(e) => Err(with_path("<stdin>", e.into())),
+            None => Ok(()),
+        };
+    }
+    if common.stdin {
+        // 構文解析と診断の表示にはソース全体が必要なので、ストリーミングするのはトークンの出力のみ
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        let mut diagnostics_writer = diagnostics_writer(options.diagnostic_format);
+        let analysis = analyze(&source, emit, &options, None);
+        let diagnostics = match &analysis {
+            Result::Ok(analysis) => analysis.warnings.clone(),
+            Err(e) => error_diagnostics(e),
+        };
+        diagnostics_writer.write("<stdin>", &source, &diagnostics)?;
+        diagnostics_writer.finish()?;
+        let Analysis { outputs, .. } =
+            analysis.map_err(|_| anyhow!("1 of 1 files failed: <stdin>"))?;
+        if options.check {
+            return match outputs.first() {
+                Some(output) if output.content != source.as_bytes() => {
+                    Err(with_path("<stdin>", anyhow!("file is not formatted")))
+                }
+                _ => Ok(()),
+            };
+        }
+        // 標準出力には主となる出力のみ書き込む
+        if let Some(output) = outputs.first() {
+            io::stdout().write_all(&output.content)?;
+        }
+        return Ok(());
+    }
+    jack_analyzer(&common.path, emit, &options)
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| anyhow!("invalid glob {:?}: {}", pattern, e))?);
+    }
+    Ok(builder.build()?)
+}
+
+// .jackignoreの1行を1つのglobとして読み込む。空行と#で始まる行は無視する
+fn read_jack_ignore(dir: &Path) -> Result<Vec<String>> {
+    let ignore_file = dir.join(JACK_IGNORE_FILE);
+    if !ignore_file.is_file() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_to_string(ignore_file)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.trim_end_matches('/').to_string())
+        .collect())
+}
+
+// --stdinの場合はカレントディレクトリ、ファイルの場合はそのディレクトリの設定を使う
+fn config_dir(common: &cli::CommonArgs) -> PathBuf {
+    let path = Path::new(&common.path);
+    match path.parent() {
+        _ if common.stdin => PathBuf::from("."),
+        _ if path.is_dir() => path.to_path_buf(),
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+// .jacklintが存在しない場合は各ルールの既定の重大度を使う
+fn read_lint_config(dir: &Path) -> Result<LintConfig> {
+    let config_file = dir.join(jack_lint::CONFIG_FILE);
+    if !config_file.is_file() {
+        return Ok(LintConfig::default());
     }
+    let config_path = config_file.display().to_string();
+    let config = LintConfig::parse(&fs::read_to_string(&config_file)?)
+        .map_err(|e| with_path(&config_path, e))?;
+    // 未知のルール名はファイルを解析する前に報告する
+    Linter::new()
+        .with_config(config.clone())
+        .map_err(|e| with_path(&config_path, e))?;
+    Ok(config)
 }
 
-fn parse_analyze_target_path(path: &Path) -> Result<Vec<PathBuf>> {
+fn parse_analyze_target_path(path: &Path, options: &Options) -> Result<Vec<PathBuf>> {
     let mut jack_files: Vec<PathBuf> = Vec::new();
     if path.is_dir() {
-        for entry in path.read_dir()? {
-            if let Ok(entry) = entry {
-                if entry.path().is_file() {
-                    match entry.path().extension() {
-                        Some(file_extension) if file_extension == JACK_FILE_EXTENSION => {
-                            jack_files.push(entry.path().to_path_buf());
-                        }
-                        _ => (),
+        let include = build_glob_set(&options.include)?;
+        let mut exclude_patterns = options.exclude.clone();
+        exclude_patterns.extend(read_jack_ignore(path)?);
+        let exclude = build_glob_set(&exclude_patterns)?;
+        let relative_path = |entry: &walkdir::DirEntry| {
+            entry
+                .path()
+                .strip_prefix(path)
+                .unwrap_or(entry.path())
+                .to_path_buf()
+        };
+        let max_depth = if options.recursive { usize::MAX } else { 1 };
+        // 除外されたディレクトリの配下は走査しない
+        for entry in WalkDir::new(path)
+            .max_depth(max_depth)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !exclude.is_match(relative_path(entry)))
+        {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                match entry.path().extension() {
+                    Some(file_extension)
+                        if file_extension == JACK_FILE_EXTENSION
+                            && (include.is_empty() || include.is_match(relative_path(&entry))) =>
+                    {
+                        jack_files.push(entry.path().to_path_buf());
                     }
+                    _ => (),
                 }
             }
         }
@@ -55,24 +252,362 @@ fn parse_analyze_target_path(path: &Path) -> Result<Vec<PathBuf>> {
     Ok(jack_files)
 }
 
-fn jack_analyzer(path_str: &str) -> Result<()> {
+fn output_file_path(jack_file: &Path, suffix: &str, extension: &str) -> PathBuf {
+    jack_file.parent().unwrap().join(format!(
+        "{}{}.{}",
+        jack_file.file_stem().unwrap().to_string_lossy(),
+        suffix,
+        extension
+    ))
+}
+
+// out_dirが指定された場合はrootからの相対パスを保ったままout_dir配下に出力する。
+// 既存の.jackファイルは整形対象そのものか、内容が同じ以前の出力の場合だけ上書きできる
+fn resolve_output_path(
+    root: &Path,
+    jack_file: &Path,
+    output: &Output,
+    out_dir: Option<&Path>,
+) -> Result<PathBuf> {
+    let output_path = output_file_path(jack_file, output.suffix, output.extension);
+    let output_path = match out_dir {
+        Some(out_dir) => match output_path.strip_prefix(root) {
+            Result::Ok(relative_path) => out_dir.join(relative_path),
+            Err(_) => out_dir.join(output_path.file_name().unwrap()),
+        },
+        None => output_path,
+    };
+    if output_path
+        .extension()
+        .is_some_and(|e| e == JACK_FILE_EXTENSION)
+        && output_path.exists()
+        && output_path.canonicalize().ok() != jack_file.canonicalize().ok()
+        && fs::read(&output_path)? != output.content
+    {
+        return Err(anyhow!(
+            "refusing to overwrite jack source: {}",
+            output_path.display()
+        ));
+    }
+    Ok(output_path)
+}
+
+// 複数行のエラーメッセージの各行にファイルパスを付与する
+fn with_path(path: &str, e: anyhow::Error) -> anyhow::Error {
+    anyhow!(e
+        .to_string()
+        .lines()
+        .map(|line| format!("{}:{}", path, line))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn diagnostics_error(diagnostics: &[Diagnostic]) -> anyhow::Error {
+    Diagnostics(diagnostics.to_vec()).into()
+}
+
+// 解析のエラーを診断に変換する。位置を持たないエラーはファイル全体に対する診断にする
+fn error_diagnostics(e: &anyhow::Error) -> Vec<Diagnostic> {
+    if let Some(Diagnostics(diagnostics)) = e.downcast_ref::<Diagnostics>() {
+        return diagnostics.clone();
+    }
+    if let Some(lex_error) = e.downcast_ref::<LexError>() {
+        return vec![lex_error.clone().into()];
+    }
+    if let Some(diagnostic) = e.downcast_ref::<Diagnostic>() {
+        return vec![diagnostic.clone()];
+    }
+    vec![Diagnostic::error(e.to_string(), Span::default())]
+}
+
+// テキストは標準エラー出力、機械可読な形式は標準出力に書き込む。
+// テキストは端末に出力する場合のみ色付けする(NO_COLORが設定されていれば色付けしない)
+fn diagnostics_writer(format: DiagnosticFormat) -> DiagnosticsWriter {
+    match format {
+        DiagnosticFormat::Text => {
+            DiagnosticsWriter::new(Arc::new(Mutex::new(io::stderr())), format)
+                .with_color(io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none())
+        }
+        DiagnosticFormat::Json | DiagnosticFormat::Sarif => {
+            DiagnosticsWriter::new(Arc::new(Mutex::new(io::stdout())), format)
+        }
+    }
+}
+
+fn write_to_vec(write: impl FnOnce(Arc<Mutex<dyn Write>>) -> Result<()>) -> Result<Vec<u8>> {
+    let buffer = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+    write(buffer.clone())?;
+    let content = std::mem::take(buffer.lock().unwrap().get_mut());
+    Ok(content)
+}
+
+// programが指定された場合は他のクラスのサブルーチン呼び出しも検査する
+fn analyze(
+    source: &str,
+    emit: Emit,
+    options: &Options,
+    program: Option<&Program>,
+) -> Result<Analysis> {
+    let tokens_xml = || -> Result<Output> {
+        let mut tokenizer = JackTokenizer::new(source.as_bytes())?;
+        Ok(Output {
+            suffix: "T",
+            extension: "xml",
+            content: write_to_vec(|w| TokenizedXmlWriter::new(w).write_xml(&mut tokenizer))?,
+        })
+    };
+    if emit == Emit::Tokens {
+        return Ok(Analysis {
+            outputs: vec![tokens_xml()?],
+            ..Default::default()
+        });
+    }
+    let tokenizer = JackTokenizer::new(source.as_bytes())?;
+    let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+    // 構文エラーはファイル内のすべてを報告する
+    let class = match compilation_engine.parse_class() {
+        (Some(class), diagnostics) if diagnostics.is_empty() => class,
+        (_, diagnostics) => return Err(diagnostics_error(&diagnostics)),
+    };
+    // 意味解析はcheckとcompileで行う。エラーがあれば警告も含めてすべて報告する
+    let symbol_table = compilation_engine.symbol_table().clone();
+    let mut warnings = Vec::new();
+    if matches!(emit, Emit::Check | Emit::Vm) {
+        let mut diagnostics = SemanticChecker::new().check(&class);
+        if let Some(program) = program {
+            diagnostics.extend(program.check(&class, symbol_table.clone()));
+        }
+        if let Some(strictness) = options.type_check {
+            diagnostics.extend(TypeChecker::new(symbol_table.clone(), strictness).check(&class));
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(diagnostics_error(&diagnostics));
+        }
+        warnings = diagnostics;
+    }
+    if emit == Emit::Lint {
+        let diagnostics = Linter::new()
+            .with_config(options.lint_config.clone())?
+            .lint(source, &class)?;
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(diagnostics_error(&diagnostics));
+        }
+        warnings = diagnostics;
+    }
+    let outputs = match emit {
+        Emit::Xml => vec![
+            Output {
+                suffix: "",
+                extension: "xml",
+                content: write_to_vec(|w| ParseTreeWriter::new(w).write_tree(&class))?,
+            },
+            // 10章の成果物に合わせてトークンのxml(XxxT.xml)も出力する
+            tokens_xml()?,
+        ],
+        Emit::ExtendedXml => vec![Output {
+            suffix: "",
+            extension: "xml",
+            content: write_to_vec(|w| {
+                ParseTreeWriter::new(w)
+                    .with_symbol_table(symbol_table)
+                    .write_tree(&class)
+            })?,
+        }],
+        Emit::Json => vec![Output {
+            suffix: "",
+            extension: "json",
+            content: write_to_vec(|w| {
+                // キーワードや記号の位置はASTにないのでトークン列から求める
+                let token_spans = TokenStream::new(source.as_bytes())
+                    .map(|lexeme| lexeme.map(|lexeme| lexeme.span))
+                    .collect::<Result<Vec<_>, _>>()?;
+                ParseTreeWriter::new(w)
+                    .with_format(JsonFormat::default())
+                    .with_token_spans(token_spans)
+                    .write_tree(&class)
+            })?,
+        }],
+        Emit::Sexp => vec![Output {
+            suffix: "",
+            extension: "sexp",
+            content: write_to_vec(|w| {
+                ParseTreeWriter::new(w)
+                    .with_format(SexpFormat::default())
+                    .write_tree(&class)
+            })?,
+        }],
+        Emit::Vm => vec![Output {
+            suffix: "",
+            extension: "vm",
+            content: write_to_vec(|w| {
+                CodeGenerator::new(VMWriter::new(w), symbol_table).generate(&class)
+            })?,
+        }],
+        Emit::Fmt => vec![Output {
+            suffix: "",
+            extension: JACK_FILE_EXTENSION,
+            content: JackFormatter::new(source)?.format(&class).into_bytes(),
+        }],
+        Emit::Tokens | Emit::Check | Emit::Lint => Vec::new(),
+    };
+    Ok(Analysis { outputs, warnings })
+}
+
+fn analyze_file(
+    root: &Path,
+    jack_file: &Path,
+    emit: Emit,
+    options: &Options,
+    program: Option<&Program>,
+) -> Result<(Vec<PathBuf>, Vec<Diagnostic>)> {
+    let source = fs::read_to_string(jack_file)?;
+    let Analysis { outputs, warnings } = analyze(&source, emit, options, program)?;
+    // fmtはout_dirが指定されない限り元のファイルを直接書き換える
+    if emit == Emit::Fmt && (options.check || options.out_dir.is_none()) {
+        let formatted = &outputs[0].content;
+        if formatted == source.as_bytes() {
+            return Ok((Vec::new(), warnings));
+        }
+        if options.check {
+            return Err(anyhow!("file is not formatted"));
+        }
+        fs::write(jack_file, formatted)?;
+        return Ok((vec![jack_file.to_path_buf()], warnings));
+    }
+    let mut output_paths = Vec::new();
+    for output in outputs {
+        let output_path =
+            resolve_output_path(root, jack_file, &output, options.out_dir.as_deref())?;
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(&output_path)?.write_all(&output.content)?;
+        output_paths.push(output_path);
+    }
+    Ok((output_paths, warnings))
+}
+
+fn program_dir(jack_file: &Path) -> PathBuf {
+    match jack_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+// 同じディレクトリの.jackファイルを1つのプログラムとみなし、クラスとシグネチャを集める。
+// 単一のファイルを指定した場合も同じディレクトリのクラスを参照する。
+// 構文エラーのあるファイルは解析できた部分のみ使い、エラーは各ファイルの解析で報告する
+fn build_programs(jack_files: &[PathBuf]) -> Result<HashMap<PathBuf, Program>> {
+    let mut programs = HashMap::new();
+    for jack_file in jack_files {
+        let dir = program_dir(jack_file);
+        if programs.contains_key(&dir) {
+            continue;
+        }
+        let mut program = Program::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry_path = entry?.path();
+            if !entry_path.is_file()
+                || entry_path
+                    .extension()
+                    .is_none_or(|e| e != JACK_FILE_EXTENSION)
+            {
+                continue;
+            }
+            let source = fs::read_to_string(&entry_path)?;
+            // 字句エラーのあるファイルは使わない。エラーはそのファイルの解析で報告する
+            let Result::Ok(tokenizer) = JackTokenizer::new(source.as_bytes()) else {
+                continue;
+            };
+            if let (Some(class), _) = CompilationEngine::new(tokenizer)?.parse_class() {
+                program.add_class(&class);
+            }
+        }
+        programs.insert(dir, program);
+    }
+    Ok(programs)
+}
+
+fn jack_analyzer(path_str: &str, emit: Emit, options: &Options) -> Result<()> {
     let path = Path::new(path_str);
-    let analyze_target_paths = parse_analyze_target_path(path)?;
-    analyze_target_paths
-        .iter()
-        .try_for_each(|jack_file| -> Result<()> {
-            let output_file_path = jack_file.parent().unwrap().join(format!(
-                "{}.{}",
-                jack_file.file_stem().unwrap().to_string_lossy().to_string(),
-                OUTPUT_FILE_EXTENSION
-            ));
-            let output_file = Arc::new(Mutex::new(File::create(&output_file_path)?));
-            let tokenizer = JackTokenizer::new(File::open(jack_file)?)
-                .expect(&format!("jack_toknizer initialize failed: {:?}", jack_file));
-            let mut compilation_engine = CompilationEngine::new(tokenizer, output_file)?;
-            compilation_engine.compile_class()?;
-            Ok(())
-        })?;
+    let analyze_target_paths = parse_analyze_target_path(path, options)?;
+    if !options.quiet {
+        println!("found {} jack files:", analyze_target_paths.len());
+        analyze_target_paths
+            .iter()
+            .for_each(|jack_file| println!("  {}", jack_file.display()));
+    }
+    let root = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(Path::new(""))
+    };
+    let programs = if matches!(emit, Emit::Check | Emit::Vm) {
+        build_programs(&analyze_target_paths)?
+    } else {
+        HashMap::new()
+    };
+    // jobsが0の場合はrayonの既定値(CPU数)を使う
+    let pool = ThreadPoolBuilder::new().num_threads(options.jobs).build()?;
+    // 結果は入力ファイルの順序で集めるので、並列に解析しても出力順は変わらない
+    let results: Vec<Result<(Vec<PathBuf>, Vec<Diagnostic>)>> = pool.install(|| {
+        analyze_target_paths
+            .par_iter()
+            .map(|jack_file| {
+                let program = programs.get(&program_dir(jack_file));
+                analyze_file(root, jack_file, emit, options, program)
+            })
+            .collect()
+    });
+
+    let mut diagnostics_writer = diagnostics_writer(options.diagnostic_format);
+    let mut failed_files = Vec::new();
+    for (jack_file, result) in analyze_target_paths.iter().zip(&results) {
+        let diagnostics = match result {
+            Result::Ok((output_paths, warnings)) => {
+                if !options.quiet {
+                    output_paths.iter().for_each(|output_path| {
+                        println!("{} -> {}", jack_file.display(), output_path.display())
+                    });
+                }
+                warnings.clone()
+            }
+            Err(e) => {
+                failed_files.push(jack_file);
+                error_diagnostics(e)
+            }
+        };
+        if !diagnostics.is_empty() {
+            // 該当行の表示に使うソースは診断がある場合のみ読み込む
+            let source = fs::read_to_string(jack_file).unwrap_or_default();
+            diagnostics_writer.write(&jack_file.display().to_string(), &source, &diagnostics)?;
+        }
+    }
+    diagnostics_writer.finish()?;
+    if !options.quiet && analyze_target_paths.len() > 1 {
+        println!(
+            "summary: {} succeeded, {} failed",
+            analyze_target_paths.len() - failed_files.len(),
+            failed_files.len()
+        );
+        for (jack_file, result) in analyze_target_paths.iter().zip(&results) {
+            let status = if result.is_ok() { "ok" } else { "failed" };
+            println!("  {:<6} {}", status, jack_file.display());
+        }
+    }
+    if !failed_files.is_empty() {
+        return Err(anyhow!(
+            "{} of {} files failed: {}",
+            failed_files.len(),
+            analyze_target_paths.len(),
+            failed_files
+                .iter()
+                .map(|f| f.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
 
     Ok(())
 }
@@ -96,7 +631,7 @@ mod tests {
     const TEST_JACK_DIR: &str = "test_files";
 
     fn create_test_file(test_dir: Option<&str>, test_file_extension: &str) -> Result<String> {
-        let test_dir = test_dir.or(Some("target/test/data")).unwrap();
+        let test_dir = test_dir.unwrap_or("target/test/data");
         fs::create_dir_all(test_dir)?;
         let mut test_file_name = Alphanumeric.sample_string(&mut rand::rng(), 5);
         test_file_name = format!("{}.{}", test_file_name, test_file_extension);
@@ -130,9 +665,416 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_resolve_output_path() -> Result<()> {
+        let output = Output {
+            suffix: "T",
+            extension: "xml",
+            content: Vec::new(),
+        };
+        let root = Path::new("projects/10");
+        let jack_file = Path::new("projects/10/Square/Main.jack");
+
+        assert_eq!(
+            resolve_output_path(root, jack_file, &output, None)?,
+            Path::new("projects/10/Square/MainT.xml")
+        );
+        assert_eq!(
+            resolve_output_path(root, jack_file, &output, Some(Path::new("build")))?,
+            Path::new("build/Square/MainT.xml")
+        );
+
+        // 出力先の既存の.jackファイルは、整形対象そのものか以前と同じ出力でなければ上書きしない
+        let jack_output = Output {
+            suffix: "",
+            extension: JACK_FILE_EXTENSION,
+            content: b"class Main {}\n".to_vec(),
+        };
+        let test_dir = "target/test/resolve_output_path";
+        let jack_file_path = create_test_file(Some(test_dir), JACK_FILE_EXTENSION)?;
+        let jack_file = Path::new(&jack_file_path);
+        assert_eq!(
+            resolve_output_path(Path::new(test_dir), jack_file, &jack_output, None)?,
+            jack_file
+        );
+        let out_dir = Path::new(test_dir).join("out");
+        let existing = out_dir.join(jack_file.file_name().unwrap());
+        fs::create_dir_all(&out_dir)?;
+        fs::write(&existing, "class Other {}\n")?;
+        let err = resolve_output_path(Path::new(test_dir), jack_file, &jack_output, Some(&out_dir))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("refusing to overwrite jack source"));
+        fs::write(&existing, &jack_output.content)?;
+        assert_eq!(
+            resolve_output_path(Path::new(test_dir), jack_file, &jack_output, Some(&out_dir))?,
+            existing
+        );
+
+        fs::remove_dir_all(test_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_jack_analyzer_when_out_dir() -> Result<()> {
+        let test_dir = "target/test/out_dir";
+        let src_dir = format!("{}/src", test_dir);
+        let out_dir = Path::new(test_dir).join("build");
+        let jack_file_path = create_test_file(Some(&src_dir), JACK_FILE_EXTENSION)?;
+        fs::write(&jack_file_path, "class Main { }")?;
+        let options = Options {
+            out_dir: Some(out_dir.clone()),
+            quiet: true,
+            ..Default::default()
+        };
+        jack_analyzer(&src_dir, Emit::Vm, &options)?;
+
+        let jack_file = Path::new(&jack_file_path);
+        let file_name = output_file_path(jack_file, "", "vm");
+        assert!(out_dir.join(file_name.file_name().unwrap()).exists());
+        assert!(!file_name.exists());
+
+        fs::remove_dir_all(test_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_jack_analyzer_continues_after_failure() -> Result<()> {
+        let test_dir = "target/test/parallel";
+        fs::create_dir_all(test_dir)?;
+        let sources = [
+            ("A.jack", "class A { }"),
+            ("B.jack", "class B { field int x }"),
+            ("C.jack", "class C { }"),
+        ];
+        for (file, source) in sources {
+            fs::write(Path::new(test_dir).join(file), source)?;
+        }
+        let options = Options {
+            quiet: true,
+            jobs: 2,
+            ..Default::default()
+        };
+        let err = jack_analyzer(test_dir, Emit::Vm, &options).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            format!("1 of 3 files failed: {}/B.jack", test_dir)
+        );
+        assert!(Path::new(test_dir).join("A.vm").exists());
+        assert!(Path::new(test_dir).join("C.vm").exists());
+
+        fs::remove_dir_all(test_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_jack_analyzer_checks_calls_across_files() -> Result<()> {
+        let test_dir = "target/test/program";
+        fs::create_dir_all(test_dir)?;
+        let main_file = Path::new(test_dir).join("Main.jack");
+        fs::write(
+            &main_file,
+            "class Main { function void main() { do Game.run(); return; } }",
+        )?;
+        fs::write(
+            Path::new(test_dir).join("Game.jack"),
+            "class Game { function void start() { do Output.println(); return; } }",
+        )?;
+        let options = Options {
+            quiet: true,
+            ..Default::default()
+        };
+        // 単一のファイルを指定しても同じディレクトリのクラスを参照する
+        let err = analyze_file(
+            Path::new(test_dir),
+            &main_file,
+            Emit::Check,
+            &options,
+            build_programs(std::slice::from_ref(&main_file))?.get(Path::new(test_dir)),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "1:45: undefined subroutine: \"Game.run\"");
+
+        fs::write(
+            Path::new(test_dir).join("Game.jack"),
+            "class Game { function void run() { return; } }",
+        )?;
+        jack_analyzer(test_dir, Emit::Check, &options)?;
+        jack_analyzer(&main_file.display().to_string(), Emit::Vm, &options)?;
+
+        // 字句エラーのある隣のファイルは、他のファイルの解析を止めずにそのファイルのエラーとして報告する
+        let broken_file = Path::new(test_dir).join("Broken.jack");
+        fs::write(
+            &broken_file,
+            "class Broken { function void f() { let x = $; } }",
+        )?;
+        jack_analyzer(&main_file.display().to_string(), Emit::Check, &options)?;
+        let err = jack_analyzer(test_dir, Emit::Vm, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("1 of 3 files failed: {}", broken_file.display())
+        );
+
+        fs::remove_dir_all(test_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_when_lint() -> Result<()> {
+        let source = "class Main { function void main() { var int a; do Main.run(); } }";
+        let err = analyze(source, Emit::Lint, &Options::default(), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1:28: missing return at the end of subroutine: \"main\"\n\
+             1:45: warning: unused variable: \"a\""
+        );
+        let codes = error_diagnostics(&err)
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, vec![Some("missing-return"), Some("unused-variable")]);
+
+        let test_dir = "target/test/lint";
+        fs::create_dir_all(test_dir)?;
+        fs::write(
+            Path::new(test_dir).join(jack_lint::CONFIG_FILE),
+            "missing-return = warn\nunused-variable = allow\n",
+        )?;
+        let options = Options {
+            lint_config: read_lint_config(Path::new(test_dir))?,
+            ..Default::default()
+        };
+        let warnings = analyze(source, Emit::Lint, &options, None)?.warnings;
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Warning);
+
+        fs::write(
+            Path::new(test_dir).join(jack_lint::CONFIG_FILE),
+            "unused-local = allow\n",
+        )?;
+        let err = read_lint_config(Path::new(test_dir)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{}/.jacklint:unknown lint rule: \"unused-local\"", test_dir)
+        );
+
+        fs::remove_dir_all(test_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_jack_analyzer_when_fmt() -> Result<()> {
+        let test_dir = "target/test/fmt";
+        fs::create_dir_all(test_dir)?;
+        let jack_file = Path::new(test_dir).join("Main.jack");
+        fs::write(&jack_file, "class Main {\n// comment\nfield int x; }")?;
+        let check = Options {
+            quiet: true,
+            check: true,
+            ..Default::default()
+        };
+        let err = jack_analyzer(test_dir, Emit::Fmt, &check).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("1 of 1 files failed: {}", jack_file.display())
+        );
+
+        let options = Options {
+            quiet: true,
+            ..Default::default()
+        };
+        jack_analyzer(test_dir, Emit::Fmt, &options)?;
+        assert_eq!(
+            fs::read_to_string(&jack_file)?,
+            "class Main {\n    // comment\n    field int x;\n}\n"
+        );
+        jack_analyzer(test_dir, Emit::Fmt, &check)?;
+
+        // out_dirへの出力は2回目以降も以前の整形結果を上書きする
+        let out_dir = Path::new("target/test/fmt_out_dir");
+        let out_dir_options = Options {
+            out_dir: Some(out_dir.to_path_buf()),
+            quiet: true,
+            ..Default::default()
+        };
+        jack_analyzer(test_dir, Emit::Fmt, &out_dir_options)?;
+        jack_analyzer(test_dir, Emit::Fmt, &out_dir_options)?;
+        assert_eq!(
+            fs::read_to_string(out_dir.join("Main.jack"))?,
+            fs::read_to_string(&jack_file)?
+        );
+        // 入力と同じディレクトリへの出力は整形対象そのものなので上書きする
+        let in_place_options = Options {
+            out_dir: Some(PathBuf::from(test_dir)),
+            quiet: true,
+            ..Default::default()
+        };
+        jack_analyzer(test_dir, Emit::Fmt, &in_place_options)?;
+        // out_dirにある別の内容の.jackファイルは上書きしない
+        let other = "class Other {}\n";
+        fs::write(out_dir.join("Main.jack"), other)?;
+        assert!(jack_analyzer(test_dir, Emit::Fmt, &out_dir_options).is_err());
+        assert_eq!(fs::read_to_string(out_dir.join("Main.jack"))?, other);
+
+        fs::remove_dir_all(out_dir)?;
+        fs::remove_dir_all(test_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli() -> Result<()> {
+        let parse = |args: &[&str]| -> Result<(String, Emit)> {
+            let cli = Cli::try_parse_from(args)?;
+            Ok((cli.command.common().path.clone(), cli.command.emit()?))
+        };
+
+        assert_eq!(
+            parse(&["JackAnalyzer", "parse"])?,
+            ("./".to_string(), Emit::Xml)
+        );
+        assert_eq!(
+            parse(&["JackAnalyzer", "compile", "Square"])?,
+            ("Square".to_string(), Emit::Vm)
+        );
+        assert_eq!(
+            parse(&["JackAnalyzer", "parse", "--format", "extended-xml"])?.1,
+            Emit::ExtendedXml
+        );
+        assert_eq!(
+            parse(&["JackAnalyzer", "parse", "--format", "json"])?.1,
+            Emit::Json
+        );
+        assert_eq!(
+            parse(&["JackAnalyzer", "parse", "--format", "sexp"])?.1,
+            Emit::Sexp
+        );
+        assert_eq!(parse(&["JackAnalyzer", "tokenize"])?.1, Emit::Tokens);
+        assert_eq!(parse(&["JackAnalyzer", "check", "-q"])?.1, Emit::Check);
+        assert!(parse(&["JackAnalyzer", "parse", "--format", "vm"]).is_err());
+        assert!(parse(&["JackAnalyzer", "compile", "Square", "--stdin"]).is_err());
+        assert_eq!(parse(&["JackAnalyzer", "fmt"])?.1, Emit::Fmt);
+        assert_eq!(parse(&["JackAnalyzer", "lint", "Square"])?.1, Emit::Lint);
+        assert!(Cli::try_parse_from(["JackAnalyzer", "fmt", "--check"])?
+            .command
+            .check());
+        assert!(parse(&["JackAnalyzer", "parse", "--check"]).is_err());
+        let type_check = |args: &[&str]| -> Result<Option<Strictness>> {
+            Ok(Cli::try_parse_from(args)?.command.type_check())
+        };
+        assert_eq!(type_check(&["JackAnalyzer", "check"])?, None);
+        assert_eq!(
+            type_check(&["JackAnalyzer", "check", "--type-check"])?,
+            Some(Strictness::Warn)
+        );
+        assert_eq!(
+            type_check(&["JackAnalyzer", "compile", "--type-check=error", "Square"])?,
+            Some(Strictness::Error)
+        );
+        assert!(parse(&["JackAnalyzer", "parse", "--type-check"]).is_err());
+        let diagnostic_format = |args: &[&str]| -> Result<DiagnosticFormat> {
+            Ok(Cli::try_parse_from(args)?.command.diagnostic_format())
+        };
+        assert_eq!(
+            diagnostic_format(&["JackAnalyzer", "check"])?,
+            DiagnosticFormat::Text
+        );
+        assert_eq!(
+            diagnostic_format(&["JackAnalyzer", "check", "--format", "sarif"])?,
+            DiagnosticFormat::Sarif
+        );
+        assert_eq!(
+            diagnostic_format(&["JackAnalyzer", "lint", "--format", "json"])?,
+            DiagnosticFormat::Json
+        );
+        assert!(parse(&["JackAnalyzer", "compile", "--format", "json"]).is_err());
+        assert!(parse(&["JackAnalyzer"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze() -> Result<()> {
+        let outputs = analyze("class Main { }", Emit::Xml, &Options::default(), None)?.outputs;
+        let names = outputs
+            .iter()
+            .map(|o| format!("{}.{}", o.suffix, o.extension))
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![".xml", "T.xml"]);
+        let outputs = analyze("class Main { }", Emit::Json, &Options::default(), None)?.outputs;
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].extension, "json");
+        assert!(
+            analyze("class Main { }", Emit::Check, &Options::default(), None)?
+                .outputs
+                .is_empty()
+        );
+
+        let err =
+            analyze("class Main { let }", Emit::Check, &Options::default(), None).unwrap_err();
+        assert_eq!(error_diagnostics(&err)[0].code, Some("syntax-error"));
+        assert_eq!(
+            with_path("Main.jack", err).to_string(),
+            "Main.jack:1:14: expected `}` in class declaration, found `let`"
+        );
+
+        // 字句解析のエラーも位置を持つ診断として報告する
+        let err = analyze("class Main { $ }", Emit::Check, &Options::default(), None).unwrap_err();
+        let diagnostics = error_diagnostics(&err);
+        assert_eq!(diagnostics[0].code, Some("illegal-character"));
+        assert_eq!(diagnostics[0].span.column, 14);
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_when_semantic_error() -> Result<()> {
+        let source = "class Main { field int a; function void main(int a) { let b = a; return; } }";
+        let err = analyze(source, Emit::Check, &Options::default(), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1:50: warning: argument shadows field: \"a\"\n1:59: undeclared variable: \"b\""
+        );
+        // 構文解析のみの出力では意味解析を行わない
+        assert!(analyze(source, Emit::Xml, &Options::default(), None).is_ok());
+
+        let source = "class Main { field int a; function void main(int a) { return; } }";
+        let warnings = analyze(source, Emit::Check, &Options::default(), None)?.warnings;
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Warning);
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_when_type_check() -> Result<()> {
+        let source = "class Main { function int main() { var int a; let a = true; return a; } }";
+        // 型検査は指定された場合のみ行う
+        assert!(analyze(source, Emit::Check, &Options::default(), None)?
+            .warnings
+            .is_empty());
+
+        let warn = Options {
+            type_check: Some(Strictness::Warn),
+            ..Default::default()
+        };
+        assert_eq!(
+            analyze(source, Emit::Check, &warn, None)?.warnings[0].to_string(),
+            "1:55: warning: type mismatch: expected \"int\", found \"boolean\""
+        );
+        let error = Options {
+            type_check: Some(Strictness::Error),
+            ..Default::default()
+        };
+        assert!(analyze(source, Emit::Vm, &error, None).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_parse_analyze_target_path_when_dirctory() -> Result<()> {
-        let test_files = vec![
+        // 以前の実行で残ったファイルを含めないように空のディレクトリから始める
+        if Path::new(TEST_DIR).exists() {
+            fs::remove_dir_all(TEST_DIR)?;
+        }
+        let test_files = [
             create_test_file(Some(TEST_DIR), JACK_FILE_EXTENSION)?,
             create_test_file(Some(TEST_DIR), JACK_FILE_EXTENSION)?,
         ];
@@ -140,13 +1082,64 @@ mod tests {
             .iter()
             .map(|f| Path::new(f).to_path_buf())
             .collect();
-        let mut actual = parse_analyze_target_path(Path::new(TEST_DIR))?;
+        let mut actual = parse_analyze_target_path(Path::new(TEST_DIR), &Options::default())?;
 
-        assert_eq!(expect.sort(), actual.sort());
+        expect.sort();
+        actual.sort();
+        assert_eq!(expect, actual);
 
-        test_files
-            .iter()
-            .try_for_each(|test_file| fs::remove_file(test_file))?;
+        fs::remove_dir_all(TEST_DIR)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_analyze_target_path_when_recursive() -> Result<()> {
+        let test_dir = Path::new("target/test/recursive");
+        for file in [
+            "A/Main.jack",
+            "A/old/Old.jack",
+            "B/Main.jack",
+            "C.jack",
+            "D.txt",
+        ] {
+            let file = test_dir.join(file);
+            fs::create_dir_all(file.parent().unwrap())?;
+            File::create(file)?;
+        }
+        fs::write(test_dir.join(JACK_IGNORE_FILE), "# old sources\nA/old/\n")?;
+        let find = |options: Options| -> Result<Vec<String>> {
+            Ok(parse_analyze_target_path(test_dir, &options)?
+                .iter()
+                .map(|p| p.strip_prefix(test_dir).unwrap().display().to_string())
+                .collect())
+        };
+
+        assert_eq!(find(Options::default())?, vec!["C.jack"]);
+        assert_eq!(
+            find(Options {
+                recursive: true,
+                ..Default::default()
+            })?,
+            vec!["A/Main.jack", "B/Main.jack", "C.jack"]
+        );
+        assert_eq!(
+            find(Options {
+                recursive: true,
+                include: vec!["A/**".to_string()],
+                ..Default::default()
+            })?,
+            vec!["A/Main.jack"]
+        );
+        assert_eq!(
+            find(Options {
+                recursive: true,
+                exclude: vec!["B".to_string(), "*.jack".to_string()],
+                ..Default::default()
+            })?,
+            Vec::<String>::new()
+        );
+
+        fs::remove_dir_all(test_dir)?;
         Ok(())
     }
 
@@ -155,30 +1148,126 @@ mod tests {
         let jack_file_paths =
             find_files_with_extension(Path::new(TEST_JACK_DIR), JACK_FILE_EXTENSION)?;
 
-        jack_file_paths
-            .iter()
-            .try_for_each(|jack_file_path| jack_analyzer(&jack_file_path))?;
+        assert!(!jack_file_paths.is_empty());
+        // チェックインしたtest_filesを汚さないようにout_dirへ出力する
+        let out_dir = Path::new("target/test/tokenized_xml_writer");
+        let options = Options {
+            out_dir: Some(out_dir.to_path_buf()),
+            quiet: true,
+            recursive: true,
+            ..Default::default()
+        };
+        jack_analyzer(TEST_JACK_DIR, Emit::Xml, &options)?;
+
+        jack_file_paths.iter().try_for_each(|jack_file_path| {
+            let jack_file = Path::new(jack_file_path);
+            let relative_path = jack_file.strip_prefix(TEST_JACK_DIR)?;
+            let actual =
+                fs::read_to_string(output_file_path(&out_dir.join(relative_path), "T", "xml"))?;
+            let expect = expected_xml(jack_file, "T")?;
 
+            assert_eq!(
+                normalize_xml(&expect),
+                normalize_xml(&actual),
+                "tokens xml mismatch: {}",
+                jack_file_path
+            );
+            Ok(())
+        })?;
+
+        fs::remove_dir_all(out_dir)?;
         Ok(())
     }
 
+    #[test]
+    fn test_jack_analyzer_writes_tokens_xml() -> Result<()> {
+        let test_dir = "target/test/tokens_xml";
+        let jack_file_path = create_test_file(Some(test_dir), JACK_FILE_EXTENSION)?;
+        fs::write(&jack_file_path, "class Main { field int x; }")?;
+        jack_analyzer(&jack_file_path, Emit::Xml, &Options::default())?;
+
+        let jack_file = Path::new(&jack_file_path);
+        let tokens_xml = fs::read_to_string(output_file_path(jack_file, "T", "xml"))?;
+        assert!(tokens_xml.starts_with("<tokens>\n<keyword> class </keyword>\n"));
+        assert!(tokens_xml.ends_with("<symbol> } </symbol>\n</tokens>"));
+        assert!(output_file_path(jack_file, "", "xml").exists());
+
+        fs::remove_dir_all(test_dir)?;
+        Ok(())
+    }
+
+    // 空白の違い(改行コードやインデント)を無視して比較するために要素ごとの行に正規化する
+    fn normalize_xml(xml: &str) -> String {
+        xml.lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn expected_xml(jack_file_path: &Path, suffix: &str) -> Result<String> {
+        let expected_path = jack_file_path
+            .parent()
+            .unwrap()
+            .join("expected")
+            .join(format!(
+                "{}{}.xml",
+                jack_file_path.file_stem().unwrap().to_string_lossy(),
+                suffix
+            ));
+        Ok(fs::read_to_string(expected_path)?)
+    }
+
     #[test]
     fn run_analyze_use_compilation_engine() -> Result<()> {
         let jack_file_paths =
             find_files_with_extension(Path::new(TEST_JACK_DIR), JACK_FILE_EXTENSION)?;
+        assert!(!jack_file_paths.is_empty());
 
         jack_file_paths.iter().try_for_each(|jack_file_path| {
             let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
             let jack_code = File::open(jack_file_path)?;
             let tokenizer = JackTokenizer::new(jack_code)?;
-            let mut compilation_engine = CompilationEngine::new(tokenizer, output.clone())?;
-            compilation_engine
+            let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+            let class = compilation_engine
                 .compile_class()
-                .expect(&format!("compilation file: {:?}", jack_file_path));
+                .unwrap_or_else(|_| panic!("compilation file: {:?}", jack_file_path));
+            ParseTreeWriter::new(output.clone()).write_tree(&class)?;
+            let output = output.lock().unwrap();
+            let actual = String::from_utf8_lossy(output.get_ref());
+            let expect = expected_xml(Path::new(jack_file_path), "")?;
+
+            assert_eq!(
+                normalize_xml(&expect),
+                normalize_xml(&actual),
+                "parse tree xml mismatch: {}",
+                jack_file_path
+            );
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    #[test]
+    fn run_analyze_use_tokenized_xml_writer_golden() -> Result<()> {
+        let jack_file_paths =
+            find_files_with_extension(Path::new(TEST_JACK_DIR), JACK_FILE_EXTENSION)?;
+        assert!(!jack_file_paths.is_empty());
+
+        jack_file_paths.iter().try_for_each(|jack_file_path| {
+            let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+            let mut tokenizer = JackTokenizer::new(File::open(jack_file_path)?)?;
+            TokenizedXmlWriter::new(output.clone()).write_xml(&mut tokenizer)?;
             let output = output.lock().unwrap();
-            let _actual = String::from_utf8_lossy(output.get_ref());
+            let actual = String::from_utf8_lossy(output.get_ref());
+            let expect = expected_xml(Path::new(jack_file_path), "T")?;
 
-            // assert_eq!(expect, actual);
+            assert_eq!(
+                normalize_xml(&expect),
+                normalize_xml(&actual),
+                "tokens xml mismatch: {}",
+                jack_file_path
+            );
             Ok(())
         })?;
         Ok(())
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use strum_macros::AsRefStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+pub enum Kind {
+    Static,
+    Field,
+    #[strum(serialize = "argument")]
+    Arg,
+    Var,
+}
+
+impl Kind {
+    pub fn is_class_scope(&self) -> bool {
+        matches!(self, Kind::Static | Kind::Field)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub var_type: String,
+    pub kind: Kind,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    symbols: HashMap<String, Symbol>,
+    counts: HashMap<Kind, u16>,
+}
+
+impl Scope {
+    fn define(&mut self, name: &str, var_type: &str, kind: Kind) -> bool {
+        if self.symbols.contains_key(name) {
+            return false;
+        }
+        let count = self.counts.entry(kind).or_default();
+        self.symbols.insert(
+            name.to_string(),
+            Symbol {
+                name: name.to_string(),
+                var_type: var_type.to_string(),
+                kind,
+                index: *count,
+            },
+        );
+        *count += 1;
+        true
+    }
+
+    fn var_count(&self, kind: Kind) -> u16 {
+        self.counts.get(&kind).copied().unwrap_or_default()
+    }
+}
+
+// クラススコープと、サブルーチン名ごとのサブルーチンスコープを保持する。
+// 構文解析後もenter_subroutineで対象のサブルーチンに切り替えて問い合わせできる
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    class_scope: Scope,
+    subroutine_scopes: HashMap<String, Scope>,
+    current_subroutine: String,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start_subroutine(&mut self, name: &str) {
+        self.subroutine_scopes
+            .insert(name.to_string(), Scope::default());
+        self.current_subroutine = name.to_string();
+    }
+
+    pub fn enter_subroutine(&mut self, name: &str) -> bool {
+        if !self.subroutine_scopes.contains_key(name) {
+            return false;
+        }
+        self.current_subroutine = name.to_string();
+        true
+    }
+
+    // 同じスコープに同名のシンボルが既に存在する場合は定義せずfalseを返す
+    pub fn define(&mut self, name: &str, var_type: &str, kind: Kind) -> bool {
+        if kind.is_class_scope() {
+            self.class_scope.define(name, var_type, kind)
+        } else {
+            self.subroutine_scopes
+                .entry(self.current_subroutine.clone())
+                .or_default()
+                .define(name, var_type, kind)
+        }
+    }
+
+    pub fn var_count(&self, kind: Kind) -> u16 {
+        if kind.is_class_scope() {
+            self.class_scope.var_count(kind)
+        } else {
+            self.subroutine_scopes
+                .get(&self.current_subroutine)
+                .map(|scope| scope.var_count(kind))
+                .unwrap_or_default()
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.subroutine_scopes
+            .get(&self.current_subroutine)
+            .and_then(|scope| scope.symbols.get(name))
+            .or_else(|| self.class_scope.symbols.get(name))
+    }
+
+    pub fn kind_of(&self, name: &str) -> Option<Kind> {
+        self.get(name).map(|symbol| symbol.kind)
+    }
+
+    pub fn type_of(&self, name: &str) -> Option<&str> {
+        self.get(name).map(|symbol| symbol.var_type.as_str())
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<u16> {
+        self.get(name).map(|symbol| symbol.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_symbol_table() {
+        let mut symbol_table = SymbolTable::new();
+        symbol_table.define("x", "int", Kind::Field);
+        symbol_table.define("y", "int", Kind::Field);
+        symbol_table.define("count", "int", Kind::Static);
+
+        symbol_table.start_subroutine("distance");
+        symbol_table.define("this", "Point", Kind::Arg);
+        symbol_table.define("other", "Point", Kind::Arg);
+        symbol_table.define("dx", "int", Kind::Var);
+        symbol_table.define("x", "boolean", Kind::Var);
+
+        assert_eq!(symbol_table.var_count(Kind::Field), 2);
+        assert_eq!(symbol_table.var_count(Kind::Static), 1);
+        assert_eq!(symbol_table.var_count(Kind::Arg), 2);
+        assert_eq!(symbol_table.var_count(Kind::Var), 2);
+        assert_eq!(symbol_table.kind_of("other"), Some(Kind::Arg));
+        assert_eq!(symbol_table.index_of("other"), Some(1));
+        assert_eq!(symbol_table.type_of("other"), Some("Point"));
+        // サブルーチンスコープがクラススコープより優先される
+        assert_eq!(symbol_table.kind_of("x"), Some(Kind::Var));
+        assert_eq!(symbol_table.index_of("x"), Some(1));
+        assert_eq!(symbol_table.kind_of("y"), Some(Kind::Field));
+        assert_eq!(symbol_table.kind_of("z"), None);
+
+        symbol_table.start_subroutine("new");
+        assert_eq!(symbol_table.var_count(Kind::Var), 0);
+        assert_eq!(symbol_table.kind_of("x"), Some(Kind::Field));
+        assert_eq!(symbol_table.kind_of("dx"), None);
+
+        assert!(symbol_table.enter_subroutine("distance"));
+        assert_eq!(symbol_table.kind_of("dx"), Some(Kind::Var));
+        assert!(!symbol_table.enter_subroutine("unknown"));
+    }
+
+    #[test]
+    fn test_define_when_duplicate() {
+        let mut symbol_table = SymbolTable::new();
+        symbol_table.start_subroutine("main");
+        assert!(symbol_table.define("a", "int", Kind::Var));
+        assert!(!symbol_table.define("a", "char", Kind::Var));
+        assert!(symbol_table.define("b", "int", Kind::Var));
+
+        assert_eq!(symbol_table.type_of("a"), Some("int"));
+        assert_eq!(symbol_table.index_of("b"), Some(1));
+        assert_eq!(symbol_table.var_count(Kind::Var), 2);
+    }
+
+    #[test]
+    fn test_kind_as_ref() {
+        assert_eq!(Kind::Arg.as_ref(), "argument");
+        assert_eq!(Kind::Var.as_ref(), "var");
+    }
+}
@@ -4,7 +4,7 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use jack_tokenizer::JackTokenizer;
+use jack_tokenizer::Token;
 
 pub struct TokenizedXmlWriter {
     writer: Arc<Mutex<dyn Write>>,
@@ -12,48 +12,40 @@ pub struct TokenizedXmlWriter {
 
 impl TokenizedXmlWriter {
     pub fn new(writer: Arc<Mutex<dyn Write>>) -> Self {
-        Self { writer: writer }
+        Self { writer }
     }
 
-    pub fn write_xml(&mut self, tokenizer: &mut JackTokenizer) -> Result<()> {
-        self.write(&format!("<tokens>\n"))?;
-        while tokenizer.has_more_tokens()? {
-            tokenizer.advance()?;
-
-            match tokenizer.token_type()? {
-                jack_tokenizer::TokenType::KeyWord => {
+    // JackTokenizerなど、トークンを順に返すものから出力する。
+    // 字句エラーを返しうるTokenStreamは呼び出し側でエラーを取り出す
+    pub fn write_xml<I>(&mut self, tokens: I) -> Result<()>
+    where
+        I: IntoIterator<Item = Token>,
+    {
+        self.write("<tokens>\n")?;
+        for token in tokens {
+            let kind = token.kind();
+            match token {
+                Token::Keyword(keyword) => {
                     self.write_xml_tag(
-                        &tokenizer.token_type()?.as_ref().to_lowercase(),
-                        &tokenizer.keyword()?.as_ref().to_lowercase(),
+                        &kind.as_ref().to_lowercase(),
+                        &keyword.as_ref().to_lowercase(),
                     )?;
                 }
-                jack_tokenizer::TokenType::Symbol => {
-                    self.write_xml_tag(
-                        &tokenizer.token_type()?.as_ref().to_lowercase(),
-                        &tokenizer.symbol()?,
-                    )?;
+                Token::Symbol(symbol) => {
+                    self.write_xml_tag(&kind.as_ref().to_lowercase(), &symbol.to_string())?;
                 }
-                jack_tokenizer::TokenType::Identifier => {
-                    self.write_xml_tag(
-                        &tokenizer.token_type()?.as_ref().to_lowercase(),
-                        &tokenizer.identifer()?,
-                    )?;
+                Token::Identifier(identifier) => {
+                    self.write_xml_tag(&kind.as_ref().to_lowercase(), &identifier)?;
                 }
-                jack_tokenizer::TokenType::IntConst => {
-                    self.write_xml_tag(
-                        &tokenizer.token_type()?.as_ref(),
-                        &tokenizer.int_val()?.to_string(),
-                    )?;
+                Token::IntConst(value) => {
+                    self.write_xml_tag(kind.as_ref(), &value.to_string())?;
                 }
-                jack_tokenizer::TokenType::StringConst => {
-                    self.write_xml_tag(
-                        &tokenizer.token_type()?.as_ref(),
-                        &tokenizer.string_val()?.to_string(),
-                    )?;
+                Token::StringConst(value) => {
+                    self.write_xml_tag(kind.as_ref(), &value)?;
                 }
             }
         }
-        self.write(&format!("</tokens>"))?;
+        self.write("</tokens>")?;
         Ok(())
     }
 
@@ -74,7 +66,7 @@ impl TokenizedXmlWriter {
     }
 
     fn write(&mut self, content: &str) -> Result<()> {
-        self.writer.lock().unwrap().write(content.as_bytes())?;
+        self.writer.lock().unwrap().write_all(content.as_bytes())?;
         self.writer.lock().unwrap().flush()?;
         Ok(())
     }
@@ -83,6 +75,7 @@ impl TokenizedXmlWriter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use jack_tokenizer::{JackTokenizer, TokenStream};
     use pretty_assertions::assert_eq;
     use std::io::Cursor;
 
@@ -123,4 +116,22 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_write_xml_from_token_stream() -> Result<()> {
+        let jack_code = "/* multi\n line */ do f(\"a\");";
+        let write = |tokens: Vec<Token>| -> Result<String> {
+            let buf = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+            TokenizedXmlWriter::new(buf.clone()).write_xml(tokens)?;
+            let xml = String::from_utf8_lossy(buf.lock().unwrap().get_ref()).to_string();
+            Ok(xml)
+        };
+
+        let stream = TokenStream::new(jack_code.as_bytes())
+            .map(|lexeme| lexeme.map(|lexeme| lexeme.token))
+            .collect::<Result<Vec<_>, _>>()?;
+        let tokenizer = JackTokenizer::new(jack_code.as_bytes())?;
+        assert_eq!(write(stream)?, write(tokenizer.collect())?);
+        Ok(())
+    }
 }
@@ -0,0 +1,565 @@
+use std::{collections::HashMap, fmt};
+
+use diagnostics::{Diagnostic, Severity};
+use jack_ast::{
+    BinaryOp, Class, Expression, KeywordConstant, ReturnType, Statement, SubroutineCall,
+    SubroutineDec, SubroutineKind, Term, UnaryOp,
+};
+use jack_tokenizer::Span;
+use symbol_table::SymbolTable;
+
+// 型の不一致をどの重大度で報告するか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    #[default]
+    Warn,
+    Error,
+}
+
+impl Strictness {
+    fn severity(&self) -> Severity {
+        match self {
+            Strictness::Warn => Severity::Warning,
+            Strictness::Error => Severity::Error,
+        }
+    }
+}
+
+// 式から推論した型。配列の要素のように型が決まらない場合はUnknownとし、何とでも互換とみなす
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JackType {
+    Int,
+    Char,
+    Boolean,
+    Class(String),
+    Null,
+    Void,
+    Unknown,
+}
+
+impl JackType {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "int" => JackType::Int,
+            "char" => JackType::Char,
+            "boolean" => JackType::Boolean,
+            "void" => JackType::Void,
+            _ => JackType::Class(name.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            JackType::Int => "int",
+            JackType::Char => "char",
+            JackType::Boolean => "boolean",
+            JackType::Class(name) => name,
+            JackType::Null => "null",
+            JackType::Void => "void",
+            JackType::Unknown => "unknown",
+        }
+    }
+
+    fn is_primitive(&self) -> bool {
+        matches!(self, JackType::Int | JackType::Char | JackType::Boolean)
+    }
+
+    // Jackはint,charとオブジェクト(アドレス)の演算を許すので、boolean以外を数値として扱う
+    fn is_numeric(&self) -> bool {
+        !matches!(self, JackType::Boolean | JackType::Void)
+    }
+
+    // foundの値をexpectedの変数に代入できるか
+    pub fn is_assignable_to(&self, expected: &JackType) -> bool {
+        match (expected, self) {
+            (JackType::Unknown, _) | (_, JackType::Unknown) => true,
+            (expected, found) if expected == found => true,
+            (JackType::Boolean | JackType::Void, _) | (_, JackType::Boolean | JackType::Void) => {
+                false
+            }
+            (_, JackType::Null) => true,
+            // Arrayは任意のオブジェクトを指すポインタとして使われる
+            (JackType::Class(expected), JackType::Class(found)) => {
+                expected == "Array" || found == "Array"
+            }
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for JackType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// サブルーチンの呼び出し規約と型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub kind: SubroutineKind,
+    pub return_type: JackType,
+    pub parameters: Vec<JackType>,
+}
+
+impl Signature {
+    pub fn new(subroutine_dec: &SubroutineDec) -> Self {
+        Self {
+            kind: subroutine_dec.kind,
+            return_type: JackType::from_name(subroutine_dec.return_type.name()),
+            parameters: subroutine_dec
+                .parameters
+                .iter()
+                .map(|parameter| JackType::from_name(parameter.var_type.name()))
+                .collect(),
+        }
+    }
+}
+
+// 式の型を推論し、代入、return、条件式、引数の型を検査する。
+// 型の分からない他クラスの呼び出しは検査しない
+pub struct TypeChecker {
+    symbol_table: SymbolTable,
+    strictness: Strictness,
+    class_name: String,
+    // サブルーチン名ごとの自クラスのシグネチャ
+    signatures: HashMap<String, Signature>,
+    return_type: ReturnType,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl TypeChecker {
+    // symbol_tableはCompilationEngineが構文解析時に作成したものを受け取る
+    pub fn new(symbol_table: SymbolTable, strictness: Strictness) -> Self {
+        Self {
+            symbol_table,
+            strictness,
+            class_name: String::new(),
+            signatures: HashMap::new(),
+            return_type: ReturnType::Void,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self, class: &Class) -> Vec<Diagnostic> {
+        self.class_name = class.name.name.clone();
+        self.signatures = class
+            .subroutine_decs
+            .iter()
+            .map(|subroutine_dec| {
+                (
+                    subroutine_dec.name.name.clone(),
+                    Signature::new(subroutine_dec),
+                )
+            })
+            .collect();
+        for subroutine_dec in &class.subroutine_decs {
+            if self
+                .symbol_table
+                .enter_subroutine(&subroutine_dec.name.name)
+            {
+                self.return_type = subroutine_dec.return_type.clone();
+                self.check_statements(&subroutine_dec.body.statements);
+            }
+        }
+        self.diagnostics
+    }
+
+    fn check_statements(&mut self, statements: &[Statement]) {
+        statements
+            .iter()
+            .for_each(|statement| self.check_statement(statement));
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let(let_statement) => {
+                let value_type = self.infer_expression(&let_statement.value);
+                match &let_statement.index {
+                    // 配列の要素は型を持たないので添字のみ検査する
+                    Some(index) => self.check_index(index),
+                    None => {
+                        let variable_type = self.type_of(&let_statement.name.name);
+                        if !value_type.is_assignable_to(&variable_type) {
+                            self.report(
+                                "type-mismatch",
+                                format!(
+                                    "type mismatch: expected {:?}, found {:?}",
+                                    variable_type.name(),
+                                    value_type.name()
+                                ),
+                                let_statement.value.span,
+                            );
+                        }
+                    }
+                }
+            }
+            Statement::If(if_statement) => {
+                self.check_condition(&if_statement.condition);
+                self.check_statements(&if_statement.then_statements);
+                if let Some(else_statements) = &if_statement.else_statements {
+                    self.check_statements(else_statements);
+                }
+            }
+            Statement::While(while_statement) => {
+                self.check_condition(&while_statement.condition);
+                self.check_statements(&while_statement.statements);
+            }
+            Statement::Do(do_statement) => {
+                self.infer_subroutine_call(&do_statement.call);
+            }
+            Statement::Return(return_statement) => {
+                match (&self.return_type, &return_statement.value) {
+                    (ReturnType::Void, Some(value)) => {
+                        self.infer_expression(value);
+                        self.report(
+                            "void-return-value",
+                            "void subroutine cannot return a value",
+                            value.span,
+                        );
+                    }
+                    (ReturnType::Type(return_type), None) => {
+                        self.report(
+                            "missing-return-value",
+                            format!("missing return value: expected {:?}", return_type.name()),
+                            return_statement.span,
+                        );
+                    }
+                    (ReturnType::Type(return_type), Some(value)) => {
+                        let return_type = JackType::from_name(return_type.name());
+                        let value_type = self.infer_expression(value);
+                        if !value_type.is_assignable_to(&return_type) {
+                            self.report(
+                                "type-mismatch",
+                                format!(
+                                    "return type mismatch: expected {:?}, found {:?}",
+                                    return_type.name(),
+                                    value_type.name()
+                                ),
+                                value.span,
+                            );
+                        }
+                    }
+                    (ReturnType::Void, None) => (),
+                }
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expression) {
+        let condition_type = self.infer_expression(condition);
+        if !condition_type.is_assignable_to(&JackType::Boolean) {
+            self.report(
+                "invalid-condition",
+                format!(
+                    "condition must be \"boolean\", found {:?}",
+                    condition_type.name()
+                ),
+                condition.span,
+            );
+        }
+    }
+
+    fn check_index(&mut self, index: &Expression) {
+        let index_type = self.infer_expression(index);
+        if !index_type.is_numeric() {
+            self.report(
+                "invalid-index",
+                format!("array index must be \"int\", found {:?}", index_type.name()),
+                index.span,
+            );
+        }
+    }
+
+    // Jackの式は演算子の優先順位を持たず左から順に評価される
+    fn infer_expression(&mut self, expression: &Expression) -> JackType {
+        let mut left = self.infer_term(&expression.term);
+        for (op, term) in &expression.rest {
+            let right = self.infer_term(term);
+            left = self.infer_binary_op(*op, left, right, term.span());
+        }
+        left
+    }
+
+    fn infer_binary_op(
+        &mut self,
+        op: BinaryOp,
+        left: JackType,
+        right: JackType,
+        span: Span,
+    ) -> JackType {
+        match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                self.check_numeric_operands(op, &left, &right, span);
+                JackType::Int
+            }
+            BinaryOp::Lt | BinaryOp::Gt => {
+                self.check_numeric_operands(op, &left, &right, span);
+                JackType::Boolean
+            }
+            BinaryOp::Eq => {
+                if !right.is_assignable_to(&left) {
+                    self.report_operands(op, &left, &right, span);
+                }
+                JackType::Boolean
+            }
+            // &,|はbooleanの論理演算とintのビット演算を兼ねる
+            BinaryOp::And | BinaryOp::Or => match (&left, &right) {
+                (JackType::Boolean, JackType::Boolean) => JackType::Boolean,
+                (JackType::Unknown, other) | (other, JackType::Unknown) => other.clone(),
+                (left, right) if left.is_numeric() && right.is_numeric() => JackType::Int,
+                _ => {
+                    self.report_operands(op, &left, &right, span);
+                    JackType::Unknown
+                }
+            },
+        }
+    }
+
+    fn check_numeric_operands(
+        &mut self,
+        op: BinaryOp,
+        left: &JackType,
+        right: &JackType,
+        span: Span,
+    ) {
+        if !left.is_numeric() || !right.is_numeric() {
+            self.report_operands(op, left, right, span);
+        }
+    }
+
+    fn report_operands(&mut self, op: BinaryOp, left: &JackType, right: &JackType, span: Span) {
+        self.report(
+            "invalid-operand",
+            format!(
+                "operator {:?} cannot be applied to {:?} and {:?}",
+                op.symbol(),
+                left.name(),
+                right.name()
+            ),
+            span,
+        );
+    }
+
+    fn infer_term(&mut self, term: &Term) -> JackType {
+        match term {
+            Term::IntegerConstant { .. } => JackType::Int,
+            Term::StringConstant { .. } => JackType::Class("String".to_string()),
+            Term::KeywordConstant { keyword, .. } => match keyword {
+                KeywordConstant::True | KeywordConstant::False => JackType::Boolean,
+                KeywordConstant::Null => JackType::Null,
+                KeywordConstant::This => JackType::Class(self.class_name.clone()),
+            },
+            Term::VarName(name) => self.type_of(&name.name),
+            Term::ArrayAccess { index, .. } => {
+                self.check_index(index);
+                JackType::Unknown
+            }
+            Term::SubroutineCall(call) => match self.infer_subroutine_call(call) {
+                JackType::Void => {
+                    self.report(
+                        "void-value",
+                        format!("void subroutine used as a value: {:?}", call.name.name),
+                        call.span,
+                    );
+                    JackType::Unknown
+                }
+                return_type => return_type,
+            },
+            Term::Parenthesized { expression, .. } => self.infer_expression(expression),
+            Term::UnaryOp { op, term, span } => {
+                let operand = self.infer_term(term);
+                match (op, &operand) {
+                    (UnaryOp::Neg, operand) if operand.is_numeric() => JackType::Int,
+                    (UnaryOp::Not, JackType::Boolean | JackType::Unknown) => operand,
+                    (UnaryOp::Not, operand) if operand.is_numeric() => JackType::Int,
+                    _ => {
+                        self.report(
+                            "invalid-operand",
+                            format!(
+                                "operator {:?} cannot be applied to {:?}",
+                                op.symbol(),
+                                operand.name()
+                            ),
+                            *span,
+                        );
+                        JackType::Unknown
+                    }
+                }
+            }
+        }
+    }
+
+    // 呼び出し先のシグネチャが分かる場合は引数の型を検査し、戻り値の型を返す
+    fn infer_subroutine_call(&mut self, call: &SubroutineCall) -> JackType {
+        let argument_types = call
+            .arguments
+            .iter()
+            .map(|argument| self.infer_expression(argument))
+            .collect::<Vec<_>>();
+        let class_name = match &call.receiver {
+            None => self.class_name.clone(),
+            Some(receiver) => match self.symbol_table.get(&receiver.name) {
+                Some(symbol) => {
+                    let receiver_type = JackType::from_name(&symbol.var_type);
+                    if receiver_type.is_primitive() {
+                        self.report(
+                            "method-on-primitive",
+                            format!(
+                                "cannot call a method on {:?}: {:?}",
+                                receiver_type.name(),
+                                receiver.name
+                            ),
+                            receiver.span,
+                        );
+                        return JackType::Unknown;
+                    }
+                    symbol.var_type.clone()
+                }
+                None => receiver.name.clone(),
+            },
+        };
+        if class_name != self.class_name {
+            return JackType::Unknown;
+        }
+        let Some(signature) = self.signatures.get(&call.name.name).cloned() else {
+            return JackType::Unknown;
+        };
+        for ((parameter, argument_type), argument) in signature
+            .parameters
+            .iter()
+            .zip(&argument_types)
+            .zip(&call.arguments)
+        {
+            if !argument_type.is_assignable_to(parameter) {
+                self.report(
+                    "type-mismatch",
+                    format!(
+                        "argument type mismatch: expected {:?}, found {:?}",
+                        parameter.name(),
+                        argument_type.name()
+                    ),
+                    argument.span,
+                );
+            }
+        }
+        signature.return_type
+    }
+
+    // 未宣言の変数はSemanticCheckerが報告するので型はUnknownとする
+    fn type_of(&self, name: &str) -> JackType {
+        self.symbol_table
+            .type_of(name)
+            .map(JackType::from_name)
+            .unwrap_or(JackType::Unknown)
+    }
+
+    fn report(&mut self, code: &'static str, message: impl Into<String>, span: Span) {
+        self.diagnostics
+            .push(Diagnostic::new(self.strictness.severity(), message, span).with_code(code));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use compilation_engine::CompilationEngine;
+    use jack_tokenizer::JackTokenizer;
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    fn check(jack_code: &str, strictness: Strictness) -> Result<Vec<String>> {
+        let tokenizer = JackTokenizer::new(Cursor::new(jack_code.to_string()))?;
+        let mut compilation_engine = CompilationEngine::new(tokenizer)?;
+        let class = compilation_engine.compile_class()?;
+        Ok(
+            TypeChecker::new(compilation_engine.symbol_table().clone(), strictness)
+                .check(&class)
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_check_when_valid() -> Result<()> {
+        let diagnostics = check(
+            r#"class Point {
+    field int x;
+    field Array cache;
+    field char c;
+    constructor Point new(int ax) {
+        let x = ax + c;
+        let cache = Array.new(x);
+        let cache[x] = "a";
+        return this;
+    }
+    method boolean equals(Point other) {
+        if (~(x > 0) & (other = null)) { return false; }
+        while (x < cache[0]) { let x = (x & 1) | -x; }
+        do draw(other);
+        return x = sum(this);
+    }
+    method int sum(Point other) { return x; }
+    method void draw(Point other) { return; }
+}"#,
+            Strictness::Warn,
+        )?;
+
+        assert_eq!(diagnostics, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_when_type_mismatch() -> Result<()> {
+        let jack_code = "class Main {
+    field boolean done;
+    function int main(int n) {
+        var Main m;
+        let m = done;
+        let n = n + done;
+        do n.run();
+        do m.draw(true);
+        if (n) { return; }
+        return n;
+    }
+    method void draw(Main other) {
+        let other = draw(this);
+        return 1;
+    }
+}";
+        let expect = vec![
+            "5:17: warning: type mismatch: expected \"Main\", found \"boolean\"",
+            "6:21: warning: operator \"+\" cannot be applied to \"int\" and \"boolean\"",
+            "7:12: warning: cannot call a method on \"int\": \"n\"",
+            "8:19: warning: argument type mismatch: expected \"Main\", found \"boolean\"",
+            "9:13: warning: condition must be \"boolean\", found \"int\"",
+            "9:18: warning: missing return value: expected \"int\"",
+            "13:21: warning: void subroutine used as a value: \"draw\"",
+            "14:16: warning: void subroutine cannot return a value",
+        ];
+
+        assert_eq!(check(jack_code, Strictness::Warn)?, expect);
+        // errorではwarning:が付かない
+        assert_eq!(
+            check(jack_code, Strictness::Error)?[0],
+            "5:17: type mismatch: expected \"Main\", found \"boolean\""
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_assignable_to() {
+        let point = JackType::Class("Point".to_string());
+        let array = JackType::Class("Array".to_string());
+
+        assert!(JackType::Char.is_assignable_to(&JackType::Int));
+        assert!(JackType::Int.is_assignable_to(&point));
+        assert!(JackType::Null.is_assignable_to(&point));
+        assert!(array.is_assignable_to(&point));
+        assert!(JackType::Unknown.is_assignable_to(&JackType::Boolean));
+        assert!(!JackType::Boolean.is_assignable_to(&point));
+        assert!(!JackType::Int.is_assignable_to(&JackType::Boolean));
+        assert!(!JackType::Class("String".to_string()).is_assignable_to(&point));
+    }
+}
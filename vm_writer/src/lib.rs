@@ -0,0 +1,127 @@
+use anyhow::Result;
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+use strum_macros::AsRefStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+pub enum Segment {
+    Constant,
+    Argument,
+    Local,
+    Static,
+    This,
+    That,
+    Pointer,
+    Temp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+pub enum Command {
+    Add,
+    Sub,
+    Neg,
+    Eq,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not,
+}
+
+pub struct VMWriter {
+    writer: Arc<Mutex<dyn Write>>,
+}
+
+impl VMWriter {
+    pub fn new(writer: Arc<Mutex<dyn Write>>) -> Self {
+        Self { writer }
+    }
+
+    pub fn write_push(&mut self, segment: Segment, index: u16) -> Result<()> {
+        self.write(&format!("push {} {}", segment.as_ref(), index))
+    }
+
+    pub fn write_pop(&mut self, segment: Segment, index: u16) -> Result<()> {
+        self.write(&format!("pop {} {}", segment.as_ref(), index))
+    }
+
+    pub fn write_arithmetic(&mut self, command: Command) -> Result<()> {
+        self.write(command.as_ref())
+    }
+
+    pub fn write_label(&mut self, label: &str) -> Result<()> {
+        self.write(&format!("label {}", label))
+    }
+
+    pub fn write_goto(&mut self, label: &str) -> Result<()> {
+        self.write(&format!("goto {}", label))
+    }
+
+    pub fn write_if(&mut self, label: &str) -> Result<()> {
+        self.write(&format!("if-goto {}", label))
+    }
+
+    pub fn write_call(&mut self, name: &str, n_args: u16) -> Result<()> {
+        self.write(&format!("call {} {}", name, n_args))
+    }
+
+    pub fn write_function(&mut self, name: &str, n_vars: u16) -> Result<()> {
+        self.write(&format!("function {} {}", name, n_vars))
+    }
+
+    pub fn write_return(&mut self) -> Result<()> {
+        self.write("return")
+    }
+
+    fn write(&mut self, command: &str) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(format!("{command}\n").as_bytes())?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_vm_writer() -> Result<()> {
+        let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+        let mut vm_writer = VMWriter::new(output.clone());
+        vm_writer.write_function("Main.main", 1)?;
+        vm_writer.write_push(Segment::Constant, 7)?;
+        vm_writer.write_pop(Segment::Local, 0)?;
+        vm_writer.write_label("WHILE_EXP0")?;
+        vm_writer.write_push(Segment::Local, 0)?;
+        vm_writer.write_arithmetic(Command::Not)?;
+        vm_writer.write_if("WHILE_END0")?;
+        vm_writer.write_call("Output.printInt", 1)?;
+        vm_writer.write_goto("WHILE_EXP0")?;
+        vm_writer.write_label("WHILE_END0")?;
+        vm_writer.write_return()?;
+        let expect = "function Main.main 1
+push constant 7
+pop local 0
+label WHILE_EXP0
+push local 0
+not
+if-goto WHILE_END0
+call Output.printInt 1
+goto WHILE_EXP0
+label WHILE_END0
+return
+";
+        let output = output.lock().unwrap();
+        let actual = String::from_utf8_lossy(output.get_ref());
+
+        assert_eq!(expect, actual);
+        Ok(())
+    }
+}